@@ -0,0 +1,186 @@
+//
+// Reentry World Benchmarks
+//
+// Compares the World's indexed lookups with the scans over every object
+// that they replaced, on generated worlds of a few thousand rooms.
+//
+//      cargo bench --bench world
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use reentry::rlib::{parse, Kind, ObjectId, Reveal, SavedObject, SavedWorld, World};
+use std::collections::BTreeMap;
+
+const ITEMS_PER_ROOM: usize = 8;
+
+fn saved_object(id: String, kind: Kind, labels: Vec<String>, location: String) -> SavedObject {
+    SavedObject {
+        id,
+        kind,
+        labels,
+        description: "something".into(),
+        location,
+        destination: String::new(),
+        prospect: String::new(),
+        details: "You see nothing special.".into(),
+        contents: "You see".into(),
+        text_go: "You can't get much closer than this.".into(),
+        fixed: false,
+        refusal: "That is fixed in place.".into(),
+        weight: 1,
+        capacity: 0,
+        health: 0,
+        properties: BTreeMap::new(),
+        reverse: None,
+        hidden: false,
+        revealed_by: String::new(),
+        reveal: Reveal::Search,
+        text_found: String::new(),
+        topics: Vec::new(),
+        dialogue: Vec::new(),
+        behaviours: Vec::new(),
+        obeys: false,
+    }
+}
+
+// A corridor of rooms joined by passages, each holding a few items
+fn generate(rooms: usize) -> World {
+    let mut objects: Vec<SavedObject> = Vec::new();
+
+    let mut player = saved_object(
+        "player".into(),
+        Kind::Actor,
+        vec!["Yourself".into()],
+        "room_0".into(),
+    );
+    player.capacity = 20;
+    objects.push(player);
+
+    for room in 0..rooms {
+        let name = format!("room_{}", room);
+        let mut location = saved_object(
+            name.to_string(),
+            Kind::Location,
+            vec![name.to_string()],
+            String::new(),
+        );
+        location.capacity = 9999;
+        objects.push(location);
+
+        for item in 0..ITEMS_PER_ROOM {
+            objects.push(saved_object(
+                format!("item_{}_{}", room, item),
+                Kind::Item,
+                vec![format!("Item {} {}", room, item), "Junk".into()],
+                name.to_string(),
+            ));
+        }
+
+        if room + 1 < rooms {
+            let next = format!("room_{}", room + 1);
+            let mut forward = saved_object(
+                format!("{}_forward", name),
+                Kind::Passage,
+                vec!["Forward".into()],
+                name.to_string(),
+            );
+            forward.destination = next.to_string();
+            objects.push(forward);
+
+            let mut aft = saved_object(
+                format!("{}_aft", next),
+                Kind::Passage,
+                vec!["Aft".into()],
+                next,
+            );
+            aft.destination = name;
+            objects.push(aft);
+        }
+    }
+
+    let saved = SavedWorld {
+        player: "player".into(),
+        objects,
+        goals: Vec::new(),
+        turn: 0,
+    };
+    saved.try_into().expect("generated world is valid")
+}
+
+fn scan_contents(world: &World, container: ObjectId) -> Vec<ObjectId> {
+    world
+        .iter()
+        .filter(|(_, object)| object.location == Some(container))
+        .map(|(pos, _)| pos)
+        .collect()
+}
+
+fn scan_labels(world: &World, noun: &str) -> Vec<String> {
+    world
+        .iter()
+        .filter(|(_, object)| {
+            !object.hidden
+                && object
+                    .labels
+                    .iter()
+                    .any(|label| label.to_lowercase() == noun)
+        })
+        .flat_map(|(_, object)| object.labels.iter().map(|label| label.to_lowercase()))
+        .collect()
+}
+
+fn scan_ids(world: &World, id: &str) -> Option<ObjectId> {
+    world
+        .iter()
+        .find(|(_, object)| object.id == id)
+        .map(|(pos, _)| pos)
+}
+
+fn lookups(c: &mut Criterion) {
+    for rooms in [100, 2_500] {
+        let world = generate(rooms);
+        let last_room = world.find_id(&format!("room_{}", rooms - 1)).unwrap();
+        let last_label = format!("item {} 0", rooms - 1);
+        let last_id = format!("item_{}_0", rooms - 1);
+
+        let mut group = c.benchmark_group(format!("{} objects", world.len()));
+        group.bench_function(BenchmarkId::new("contents", "scan"), |b| {
+            b.iter(|| scan_contents(&world, black_box(last_room)))
+        });
+        group.bench_function(BenchmarkId::new("contents", "index"), |b| {
+            b.iter(|| world.contents_of(black_box(last_room)).to_vec())
+        });
+        group.bench_function(BenchmarkId::new("label", "scan"), |b| {
+            b.iter(|| scan_labels(&world, black_box(&last_label)))
+        });
+        group.bench_function(BenchmarkId::new("label", "index"), |b| {
+            b.iter(|| world.labels_meant_by(black_box(&last_label)))
+        });
+        group.bench_function(BenchmarkId::new("id", "scan"), |b| {
+            b.iter(|| scan_ids(&world, black_box(&last_id)))
+        });
+        group.bench_function(BenchmarkId::new("id", "index"), |b| {
+            b.iter(|| world.find_id(black_box(&last_id)))
+        });
+        group.finish();
+    }
+}
+
+fn commands(c: &mut Criterion) {
+    let world = generate(2_500);
+
+    let mut group = c.benchmark_group(format!("{} objects", world.len()));
+    for input_str in ["look", "get item 0 3", "go forward"] {
+        group.bench_function(BenchmarkId::new("command", input_str), |b| {
+            // By reference, so dropping the world is not timed
+            b.iter_batched_ref(
+                || world.clone(),
+                |world| world.update_state(&parse(input_str.to_string())),
+                criterion::BatchSize::LargeInput,
+            )
+        });
+    }
+    group.bench_function("load", |b| b.iter(|| generate(black_box(2_500))));
+    group.finish();
+}
+
+criterion_group!(benches, lookups, commands);
+criterion_main!(benches);
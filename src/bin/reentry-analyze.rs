@@ -0,0 +1,144 @@
+//
+// Reentry Analyze
+//
+// Reports unreachable places, unobtainable items and whether a Reentry
+// game file can be won
+use clap::{Parser, ValueEnum};
+use reentry::rlib::analysis::{self, WinCondition};
+use reentry::rlib::{ObjectId, World};
+use serde::Serialize;
+use std::process::ExitCode;
+
+// Exit status when the search stopped before it could tell whether the
+// game can be won
+const EXIT_UNKNOWN: u8 = 2;
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum Format {
+    Human,
+    Json,
+}
+
+#[derive(Parser, Debug)]
+#[command(version, about = "Check that a Reentry game file can be completed.")]
+struct Cli {
+    /// Game file to analyze
+    #[arg(value_name = "FILE")]
+    game_file: String,
+
+    /// Stop exploring after this many game states
+    #[arg(short, long, value_name = "STATES", default_value_t = 250_000)]
+    max_states: usize,
+
+    /// Output format
+    #[arg(short, long, value_enum, default_value_t = Format::Human)]
+    format: Format,
+}
+
+#[derive(Serialize, Debug)]
+struct Report {
+    file: String,
+    states_explored: usize,
+    complete: bool,
+    unreachable_locations: Vec<String>,
+    unobtainable_items: Vec<String>,
+    win_condition: WinCondition,
+    winning_path: Option<Vec<String>>,
+    dead_ends: usize,
+    dead_end_path: Option<Vec<String>>,
+}
+
+fn print_list(heading: &str, items: &[String]) {
+    println!("{}:", heading);
+    if items.is_empty() {
+        println!("    none");
+    }
+    for item in items {
+        println!("    {}", item);
+    }
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    let world = match World::read_from_file(&cli.game_file) {
+        Ok(world) => world,
+        Err(load_err) => {
+            eprintln!("ERROR - unable to load '{}': {}", cli.game_file, load_err);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let result = analysis::analyse(&world, cli.max_states);
+    let label = |pos: &ObjectId| world[*pos].labels[0].to_string();
+
+    let report = Report {
+        file: cli.game_file,
+        states_explored: result.states_explored,
+        complete: result.complete,
+        unreachable_locations: result.unreachable_locations.iter().map(label).collect(),
+        unobtainable_items: result.unobtainable_items.iter().map(label).collect(),
+        win_condition: result.win_condition,
+        winning_path: result.winning_path,
+        dead_ends: result.dead_ends,
+        dead_end_path: result.dead_end_path,
+    };
+
+    match cli.format {
+        Format::Human => {
+            println!(
+                "Explored {} states{}.",
+                report.states_explored,
+                if report.complete {
+                    ""
+                } else {
+                    " before reaching the limit"
+                }
+            );
+            let partial = if report.complete { "" } else { " (so far)" };
+            print_list(
+                &format!("Unreachable locations{}", partial),
+                &report.unreachable_locations,
+            );
+            print_list(
+                &format!("Unobtainable items{}", partial),
+                &report.unobtainable_items,
+            );
+
+            match (&report.win_condition, &report.winning_path) {
+                (WinCondition::NotDefined, _) => println!("Win condition: none defined"),
+                (WinCondition::Reachable, Some(path)) => {
+                    println!("Win condition: reachable in {} moves", path.len());
+                    print_list("Shortest win", path);
+                }
+                (WinCondition::Unknown, _) => {
+                    println!("Win condition: unknown, try a larger --max-states")
+                }
+                (_, _) => println!("Win condition: NOT reachable"),
+            }
+
+            if report.dead_ends > 0 {
+                println!(
+                    "Dead ends: {} states from which the game cannot be won",
+                    report.dead_ends
+                );
+                if let Some(path) = &report.dead_end_path {
+                    print_list("Shortest way into a dead end", path);
+                }
+            }
+        }
+        Format::Json => match serde_json::to_string_pretty(&report) {
+            Ok(json) => println!("{}", json),
+            Err(json_err) => {
+                eprintln!("ERROR - unable to write report: {}", json_err);
+                return ExitCode::FAILURE;
+            }
+        },
+    }
+
+    match report.win_condition {
+        WinCondition::NotReachable => ExitCode::FAILURE,
+        WinCondition::Unknown => ExitCode::from(EXIT_UNKNOWN),
+        _ => ExitCode::SUCCESS,
+    }
+}
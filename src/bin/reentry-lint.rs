@@ -0,0 +1,89 @@
+//
+// Reentry Lint
+//
+// Reports problems in Reentry game files
+use clap::{Parser, ValueEnum};
+use reentry::rlib::lint::{self, Diagnostic, Severity};
+use reentry::rlib::SavedWorld;
+use serde::Serialize;
+use std::process::ExitCode;
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum Format {
+    Human,
+    Json,
+}
+
+#[derive(Parser, Debug)]
+#[command(version, about = "Check Reentry game files for problems.")]
+struct Cli {
+    /// Game files to check
+    #[arg(required = true, value_name = "FILE")]
+    game_files: Vec<String>,
+
+    /// Output format
+    #[arg(short, long, value_enum, default_value_t = Format::Human)]
+    format: Format,
+}
+
+#[derive(Serialize, Debug)]
+struct FileReport {
+    file: String,
+    load_error: Option<String>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let mut reports: Vec<FileReport> = Vec::new();
+
+    for game_file in cli.game_files {
+        let report = match SavedWorld::read_from_file(&game_file) {
+            Ok(saved_world) => FileReport {
+                diagnostics: lint::lint(&saved_world),
+                file: game_file,
+                load_error: None,
+            },
+            Err(load_err) => FileReport {
+                file: game_file,
+                load_error: Some(load_err.to_string()),
+                diagnostics: Vec::new(),
+            },
+        };
+        reports.push(report);
+    }
+
+    match cli.format {
+        Format::Human => {
+            for report in &reports {
+                if let Some(load_err) = &report.load_error {
+                    println!("{}: error: {}", report.file, load_err);
+                }
+                for diagnostic in &report.diagnostics {
+                    println!("{}: {}", report.file, diagnostic);
+                }
+            }
+        }
+        Format::Json => match serde_json::to_string_pretty(&reports) {
+            Ok(json) => println!("{}", json),
+            Err(json_err) => {
+                eprintln!("ERROR - unable to write report: {}", json_err);
+                return ExitCode::FAILURE;
+            }
+        },
+    }
+
+    let failed = reports.iter().any(|report| {
+        report.load_error.is_some()
+            || report
+                .diagnostics
+                .iter()
+                .any(|diagnostic| diagnostic.severity == Severity::Error)
+    });
+
+    if failed {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
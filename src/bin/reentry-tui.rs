@@ -0,0 +1,296 @@
+//
+// Reentry TUI
+//
+// A full screen front end for Reentry, with a status line, a transcript
+// that can be scrolled back through, and an input line with history. It
+// plays the game through a Session, just like the plain terminal mode.
+use clap::Parser;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Position};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+use reentry::rlib::session::Session;
+use reentry::rlib::{Settings, World};
+use std::io;
+use std::process::ExitCode;
+
+const GAME_FILE_LOC: &str = "./game_file.ron";
+const PROMPT: &str = "> ";
+
+#[derive(Parser, Debug)]
+#[command(version, about = "Reentry. A space adventure, full screen.")]
+struct Cli {
+    /// Game file to load
+    #[arg(short, long, value_name = "FILE", default_value = GAME_FILE_LOC)]
+    game_file: String,
+
+    /// Restore a saved game instead of starting from the game file
+    #[arg(short, long, value_name = "SAVE_FILE")]
+    restore: Option<String>,
+}
+
+// Session output is styled with ANSI escapes, which are turned back into
+// styles for the transcript pane
+fn styled_line(text: &str) -> Line<'static> {
+    let mut spans: Vec<Span<'static>> = Vec::new();
+    let mut style = Style::default();
+    let mut rest = text;
+
+    while let Some(start) = rest.find("\x1b[") {
+        if start > 0 {
+            spans.push(Span::styled(rest[..start].to_string(), style));
+        }
+        let Some(end) = rest[start..].find('m') else {
+            break;
+        };
+        for code in rest[start + 2..start + end].split(';') {
+            style = match code {
+                "" | "0" => Style::default(),
+                "1" => style.add_modifier(Modifier::BOLD),
+                "3" => style.add_modifier(Modifier::ITALIC),
+                "4" => style.add_modifier(Modifier::UNDERLINED),
+                "30" => style.fg(Color::Black),
+                "31" => style.fg(Color::Red),
+                "32" => style.fg(Color::Green),
+                "33" => style.fg(Color::Yellow),
+                "34" => style.fg(Color::Blue),
+                "35" => style.fg(Color::Magenta),
+                "36" => style.fg(Color::Cyan),
+                "37" => style.fg(Color::White),
+                _ => style,
+            };
+        }
+        rest = &rest[start + end + 1..];
+    }
+
+    if !rest.is_empty() {
+        spans.push(Span::styled(rest.to_string(), style));
+    }
+    Line::from(spans)
+}
+
+struct App {
+    session: Session,
+    lines: Vec<Line<'static>>,
+    // How many lines the transcript is scrolled up from the bottom
+    scroll_back: usize,
+    page_height: usize,
+    input: String,
+    // Position in the input, counted in characters
+    cursor: usize,
+    history_pos: Option<usize>,
+    quit: bool,
+}
+
+impl App {
+    fn new(world: World) -> App {
+        let settings = Settings {
+            color: true,
+            ..Settings::default()
+        };
+        App {
+            session: Session::new(world, settings),
+            lines: Vec::new(),
+            scroll_back: 0,
+            page_height: 0,
+            input: String::new(),
+            cursor: 0,
+            history_pos: None,
+            quit: false,
+        }
+    }
+
+    fn add_output(&mut self, output: &str) {
+        self.lines.extend(output.lines().map(styled_line));
+        self.scroll_back = 0;
+    }
+
+    fn byte_pos(&self) -> usize {
+        self.input
+            .char_indices()
+            .nth(self.cursor)
+            .map_or(self.input.len(), |(pos, _)| pos)
+    }
+
+    fn submit(&mut self) {
+        if self.session.is_over() {
+            self.quit = true;
+            return;
+        }
+
+        let input_str = std::mem::take(&mut self.input);
+        self.cursor = 0;
+        self.history_pos = None;
+
+        self.lines.push(Line::from(Span::styled(
+            format!("{}{}", PROMPT, input_str),
+            Style::default().add_modifier(Modifier::BOLD),
+        )));
+        let response = self.session.step(&input_str);
+        self.add_output(&response.output);
+        self.lines.push(Line::default());
+
+        if response.game_over {
+            self.add_output("Press Enter to leave.");
+        }
+    }
+
+    fn recall(&mut self, pos: Option<usize>) {
+        self.history_pos = pos;
+        self.input = match pos {
+            Some(pos) => self.session.history[pos].clone(),
+            None => String::new(),
+        };
+        self.cursor = self.input.chars().count();
+    }
+
+    fn max_scroll_back(&self) -> usize {
+        self.lines.len().saturating_sub(self.page_height)
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) {
+        let history_len = self.session.history.len();
+
+        match key.code {
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.quit = true;
+            }
+            KeyCode::Esc => self.quit = true,
+            KeyCode::Enter => self.submit(),
+            KeyCode::Char(ch) => {
+                let pos = self.byte_pos();
+                self.input.insert(pos, ch);
+                self.cursor += 1;
+            }
+            KeyCode::Backspace if self.cursor > 0 => {
+                self.cursor -= 1;
+                let pos = self.byte_pos();
+                self.input.remove(pos);
+            }
+            KeyCode::Delete if self.cursor < self.input.chars().count() => {
+                let pos = self.byte_pos();
+                self.input.remove(pos);
+            }
+            KeyCode::Left => self.cursor = self.cursor.saturating_sub(1),
+            KeyCode::Right => self.cursor = (self.cursor + 1).min(self.input.chars().count()),
+            KeyCode::Home => self.cursor = 0,
+            KeyCode::End => self.cursor = self.input.chars().count(),
+            KeyCode::Up if history_len > 0 => {
+                let pos = match self.history_pos {
+                    Some(pos) => pos.saturating_sub(1),
+                    None => history_len - 1,
+                };
+                self.recall(Some(pos));
+            }
+            KeyCode::Down => match self.history_pos {
+                Some(pos) if pos + 1 < history_len => self.recall(Some(pos + 1)),
+                Some(_) => self.recall(None),
+                None => (),
+            },
+            KeyCode::PageUp => {
+                self.scroll_back =
+                    (self.scroll_back + self.page_height.max(1)).min(self.max_scroll_back());
+            }
+            KeyCode::PageDown => {
+                self.scroll_back = self.scroll_back.saturating_sub(self.page_height.max(1));
+            }
+            _ => (),
+        }
+    }
+
+    fn status(&self) -> String {
+        let world = &self.session.world;
+        let location = world
+            .player_location()
+            .map(|location| world[location].labels[0].clone())
+            .unwrap_or_default();
+        let mut status = format!(" {}  |  Turn {}", location, world.turn);
+        if !world.goals.is_empty() {
+            status += &format!("  |  Goals {}/{}", world.goals_met(), world.goals.len());
+        }
+        if self.scroll_back > 0 {
+            status += &format!("  |  {} lines back", self.scroll_back);
+        }
+        status
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [status_area, transcript_area, input_area] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Min(3),
+            Constraint::Length(3),
+        ])
+        .areas(frame.area());
+
+        frame.render_widget(
+            Paragraph::new(self.status()).style(Style::default().add_modifier(Modifier::REVERSED)),
+            status_area,
+        );
+
+        // Output is wrapped by the Session, so keep it to the pane's width
+        self.session.settings.width = Some(transcript_area.width.saturating_sub(2) as usize);
+        self.page_height = transcript_area.height.saturating_sub(2) as usize;
+        self.scroll_back = self.scroll_back.min(self.max_scroll_back());
+        let top = self.max_scroll_back() - self.scroll_back;
+        frame.render_widget(
+            Paragraph::new(self.lines.clone())
+                .block(Block::default().borders(Borders::ALL))
+                .scroll((top as u16, 0)),
+            transcript_area,
+        );
+
+        frame.render_widget(
+            Paragraph::new(format!("{}{}", PROMPT, self.input))
+                .block(Block::default().borders(Borders::ALL)),
+            input_area,
+        );
+        let cursor_x = input_area.x + 1 + (PROMPT.len() + self.cursor) as u16;
+        frame.set_cursor_position(Position::new(cursor_x, input_area.y + 1));
+    }
+}
+
+fn run(terminal: &mut DefaultTerminal, app: &mut App) -> io::Result<()> {
+    // Lay out once so the introduction is wrapped to the pane
+    terminal.draw(|frame| app.draw(frame))?;
+    let intro = app.session.intro();
+    app.add_output(&intro);
+    app.lines.push(Line::default());
+
+    while !app.quit {
+        terminal.draw(|frame| app.draw(frame))?;
+        if let Event::Key(key) = event::read()? {
+            if key.kind == KeyEventKind::Press {
+                app.handle_key(key);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    let world_file = cli.restore.as_deref().unwrap_or(&cli.game_file);
+    let world = match World::read_from_file(world_file) {
+        Ok(world) => world,
+        Err(load_err) => {
+            eprintln!("ERROR - unable to load '{}': {}", world_file, load_err);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut app = App::new(world);
+    let mut terminal = ratatui::init();
+    let result = run(&mut terminal, &mut app);
+    ratatui::restore();
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(io_err) => {
+            eprintln!("ERROR - {}", io_err);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,7 @@
+//
+// Reentry
+//
+// A game by Riskpeep
+//
+// The library is shared by the game and its tools
+pub mod rlib;
@@ -2,62 +2,441 @@
 // Reentry
 //
 // A game by Riskpeep
-pub mod rlib;
+use clap::{Parser, Subcommand, ValueEnum};
+use reentry::rlib;
+use reentry::rlib::batch::{self, Ending};
+use reentry::rlib::editor::EditorInput;
+use reentry::rlib::map::{MapFormat, MapOptions};
+use reentry::rlib::session::Session;
+use reentry::rlib::stream::{
+    ChainInput, FileInput, InputSource, MemoryInput, OutputSink, StdinInput, StdoutOutput,
+};
+use reentry::rlib::transcript::{self, Transcript};
+use std::env;
+use std::fs::{read_to_string, write};
+use std::io::{self, IsTerminal};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
 
 // Game file location
 const GAME_FILE_LOC: &str = "./game_file.ron";
+// Command history, kept in the home directory
+const HISTORY_FILE: &str = ".reentry_history";
 
-fn main() {
-    let world_res = init_game(GAME_FILE_LOC);
+// Exit statuses for --batch, besides success and failure
+const EXIT_COMMAND_FAILED: u8 = 2;
+const EXIT_GAME_OVER: u8 = 3;
 
-    match world_res {
-        Ok(world) => {
-            //
-            // Run Game
-            do_game(world);
-        }
+#[derive(Parser, Debug)]
+#[command(version, about = "Reentry. A space adventure.")]
+struct Cli {
+    /// Game file to load
+    #[arg(short, long, value_name = "FILE", default_value = GAME_FILE_LOC)]
+    game_file: String,
+
+    /// Restore a saved game, as written with --batch and --final-state,
+    /// instead of starting from the game file
+    #[arg(short, long, value_name = "SAVE_FILE")]
+    restore: Option<String>,
+
+    /// Run the commands in FILE (one per line) before reading from the keyboard
+    #[arg(short, long, value_name = "FILE")]
+    commands: Option<String>,
+
+    /// Write a script of the game, with timestamps, to FILE
+    #[arg(short, long, value_name = "FILE")]
+    script: Option<String>,
+
+    /// Replay the commands in a script or transcript before reading from the keyboard
+    #[arg(long, value_name = "FILE", conflicts_with = "commands")]
+    replay: Option<String>,
+
+    /// Play the commands in FILE ('-' for standard input) without a keyboard, then exit.
+    /// Exits with 2 if a command fails and 3 if the game ends.
+    #[arg(short, long, value_name = "FILE", conflicts_with_all = ["commands", "replay"])]
+    batch: Option<String>,
+
+    /// With --batch, print only the final state of the world, in the game file format
+    #[arg(long, requires = "batch")]
+    final_state: bool,
+
+    /// Output width in columns, or 0 to turn off wrapping [default: terminal width]
+    #[arg(short, long, value_name = "COLUMNS")]
+    width: Option<usize>,
+
+    /// Disable coloured output (always off when not writing to a terminal)
+    #[arg(long)]
+    no_color: bool,
+
+    /// Keep command history in FILE [default: ~/.reentry_history]
+    #[arg(long, value_name = "FILE")]
+    history: Option<PathBuf>,
+
+    /// Print debugging information while playing
+    #[arg(short, long)]
+    debug: bool,
+
+    #[command(subcommand)]
+    tool: Option<Tool>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Tool {
+    /// Draw the locations and passages of the game as a graph
+    Map {
+        /// Graph format to write
+        #[arg(short, long, value_enum, default_value_t = Format::Dot)]
+        format: Format,
+
+        /// Also draw which object holds which
+        #[arg(short, long)]
+        containment: bool,
+
+        /// Write the graph to FILE instead of the screen
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<String>,
+    },
+
+    /// Play transcripts against the game and report any differences
+    Test {
+        /// Transcripts to play
+        #[arg(required = true, value_name = "TRANSCRIPT")]
+        transcripts: Vec<String>,
+    },
+
+    /// Play interactively, recording a transcript of the session
+    Record {
+        /// Transcript file to write
+        #[arg(value_name = "TRANSCRIPT")]
+        transcript: String,
+    },
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum Format {
+    Dot,
+    Mermaid,
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    let settings = rlib::Settings {
+        width: cli.width,
+        color: !cli.no_color && io::stdout().is_terminal(),
+        debug: cli.debug,
+    };
+
+    // A save file is a complete world, so it replaces the game file
+    let world_file = cli.restore.as_deref().unwrap_or(&cli.game_file);
+    let world_res = init_game(world_file);
+
+    let world = match world_res {
+        Ok(world) => world,
         Err(file_err) => {
             //
             // Shutdown and exit with error
-            println!("ERROR - {}", file_err);
+            eprintln!("ERROR - unable to load '{}': {}", world_file, file_err);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match cli.tool {
+        Some(Tool::Map {
+            format,
+            containment,
+            output,
+        }) => return do_map(&world, format, containment, output),
+        Some(Tool::Test { transcripts }) => return do_test(&world, transcripts),
+        Some(Tool::Record { transcript }) => return do_record(world, settings, transcript),
+        None => {}
+    }
+
+    if let Some(batch_file) = cli.batch {
+        // Batch output is not wrapped unless asked for
+        let settings = rlib::Settings {
+            width: Some(cli.width.unwrap_or(0)),
+            ..settings
+        };
+        return do_batch(world, &batch_file, settings, cli.final_state);
+    }
+
+    let history_file = cli
+        .history
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(HISTORY_FILE)));
+
+    // Commands from a file or a replayed script are played first, then
+    // the keyboard takes over
+    let scripted: Option<Box<dyn InputSource>> = match (cli.commands, cli.replay) {
+        (Some(commands_file), _) => match FileInput::open(&commands_file) {
+            Ok(commands) => Some(Box::new(commands)),
+            Err(file_err) => {
+                eprintln!(
+                    "ERROR - unable to read commands from '{}': {}",
+                    commands_file, file_err
+                );
+                return ExitCode::FAILURE;
+            }
+        },
+        (None, Some(replay_file)) => match load_transcript(&replay_file) {
+            Ok(replay) => Some(Box::new(MemoryInput {
+                lines: replay.inputs().into(),
+            })),
+            Err(load_err) => {
+                eprintln!("ERROR - unable to replay '{}': {}", replay_file, load_err);
+                return ExitCode::FAILURE;
+            }
+        },
+        (None, None) => None,
+    };
+
+    //
+    // Run Game
+    let game_res = match scripted {
+        Some(scripted) => do_game(
+            world,
+            &mut ChainInput::new(scripted, keyboard(history_file)),
+            &mut StdoutOutput,
+            settings,
+            cli.script,
+        ),
+        None => do_game(
+            world,
+            &mut keyboard(history_file),
+            &mut StdoutOutput,
+            settings,
+            cli.script,
+        ),
+    };
+
+    match game_res {
+        Ok(_) => ExitCode::SUCCESS,
+        Err(io_err) => {
+            eprintln!("ERROR - {}", io_err);
+            ExitCode::FAILURE
         }
     }
 }
 
-fn init_game(file_loc: &str) -> Result<rlib::World, std::io::Error> {
+fn do_map(
+    world: &rlib::World,
+    format: Format,
+    containment: bool,
+    output: Option<String>,
+) -> ExitCode {
+    let options = MapOptions {
+        format: match format {
+            Format::Dot => MapFormat::Dot,
+            Format::Mermaid => MapFormat::Mermaid,
+        },
+        containment,
+    };
+    let graph = rlib::map::export(world, &options);
+
+    match output {
+        None => print!("{}", graph),
+        Some(output_file) => {
+            if let Err(file_err) = write(&output_file, graph) {
+                eprintln!("ERROR - unable to write '{}': {}", output_file, file_err);
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+
+    ExitCode::SUCCESS
+}
+
+fn do_batch(
+    world: rlib::World,
+    batch_file: &str,
+    settings: rlib::Settings,
+    final_state: bool,
+) -> ExitCode {
+    let mut commands: Box<dyn InputSource> = if batch_file == "-" {
+        Box::new(StdinInput)
+    } else {
+        match FileInput::open(batch_file) {
+            Ok(commands) => Box::new(commands),
+            Err(file_err) => {
+                eprintln!(
+                    "ERROR - unable to read commands from '{}': {}",
+                    batch_file, file_err
+                );
+                return ExitCode::FAILURE;
+            }
+        }
+    };
+
+    let mut session = Session::new(world, settings);
+    let run = batch::run(&mut session, &mut commands);
+
+    if final_state {
+        match ron::ser::to_string_pretty(&session.world, ron::ser::PrettyConfig::default()) {
+            Ok(state) => println!("{}", state),
+            Err(ron_err) => {
+                eprintln!("ERROR - unable to write the final state: {}", ron_err);
+                return ExitCode::FAILURE;
+            }
+        }
+    } else {
+        print!("{}", run.output);
+    }
+
+    match run.ending {
+        Ending::OutOfCommands => ExitCode::SUCCESS,
+        Ending::Failed {
+            command,
+            input,
+            kind,
+        } => {
+            eprintln!("FAILED - command {} '{}': {:?}", command, input, kind);
+            ExitCode::from(EXIT_COMMAND_FAILED)
+        }
+        Ending::Won | Ending::Quit => ExitCode::from(EXIT_GAME_OVER),
+    }
+}
+
+fn load_transcript(transcript_file: &str) -> Result<Transcript, String> {
+    read_to_string(transcript_file)
+        .map_err(|file_err| file_err.to_string())
+        .and_then(|data| Transcript::parse(&data).map_err(|parse_err| parse_err.to_string()))
+}
+
+fn do_test(world: &rlib::World, transcripts: Vec<String>) -> ExitCode {
+    let mut failed: usize = 0;
+
+    for transcript_file in &transcripts {
+        let transcript = match load_transcript(transcript_file) {
+            Ok(transcript) => transcript,
+            Err(message) => {
+                println!("ERROR {}: {}", transcript_file, message);
+                failed += 1;
+                continue;
+            }
+        };
+
+        // Every transcript starts from the beginning of the game
+        let failures = transcript.run(world.clone());
+
+        if failures.is_empty() {
+            println!("PASS {} ({} steps)", transcript_file, transcript.len());
+        } else {
+            println!("FAIL {}", transcript_file);
+            for failure in failures {
+                print!("  {}", failure);
+            }
+            failed += 1;
+        }
+    }
+
+    println!();
+    println!("{} passed, {} failed", transcripts.len() - failed, failed);
+
+    if failed == 0 {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+fn do_record(world: rlib::World, settings: rlib::Settings, transcript_file: String) -> ExitCode {
+    // Transcripts are played back unwrapped and without colour, so they
+    // are recorded the same way
+    let settings = rlib::Settings {
+        width: Some(0),
+        color: false,
+        ..settings
+    };
+    let mut session = Session::new(world, settings);
+    let mut recording = String::from("# Recorded with 'reentry record'\n");
+
+    while let Some(input_str) = rlib::read_input(&mut StdinInput, &mut StdoutOutput) {
+        if input_str.trim().is_empty() {
+            continue;
+        }
+
+        let response = session.step(&input_str);
+        recording += &transcript::record(&input_str, &response.output);
+        if rlib::update_screen(&mut StdoutOutput, response.output).is_err() {
+            break;
+        }
+
+        if response.game_over {
+            break;
+        }
+    }
+
+    match write(&transcript_file, recording) {
+        Ok(_) => ExitCode::SUCCESS,
+        Err(file_err) => {
+            eprintln!(
+                "ERROR - unable to write '{}': {}",
+                transcript_file, file_err
+            );
+            ExitCode::FAILURE
+        }
+    }
+}
+
+// The keyboard gets line editing when it is a terminal
+fn keyboard(history_file: Option<PathBuf>) -> Box<dyn InputSource> {
+    if io::stdin().is_terminal() {
+        match EditorInput::new(history_file) {
+            Ok(editor) => return Box::new(editor),
+            Err(editor_err) => eprintln!("WARNING - line editing is unavailable: {}", editor_err),
+        }
+    }
+    Box::new(StdinInput)
+}
+
+fn init_game(file_loc: &str) -> Result<rlib::World, rlib::LoadError> {
     // Read the game file and return the returned world.
     // Bubble up any error result
     rlib::World::read_from_file(file_loc)
 }
 
-fn do_game(mut world: rlib::World) {
-    let mut command: rlib::Command;
-    let mut output: String;
+fn do_game(
+    world: rlib::World,
+    input: &mut impl InputSource,
+    sink: &mut impl OutputSink,
+    settings: rlib::Settings,
+    script_file: Option<String>,
+) -> io::Result<()> {
+    let mut session = Session::new(world, settings);
+    if let Some(script_file) = script_file {
+        session.start_script(&script_file).map_err(|io_err| {
+            io::Error::new(
+                io_err.kind(),
+                format!("unable to write a script to '{}': {}", script_file, io_err),
+            )
+        })?;
+    }
 
     //
     // Introduction and Setup
     //
-    println!("Welcome to Reentry. A space adventure.");
-    println!();
-    println!("You awake in darkness with a pounding headache.");
-    println!("An alarm is flashing and beeping loudly. This doesn't help your headache.");
-    println!();
+    sink.write(&session.intro())?;
+    sink.write("\n")?;
 
     //
     // Main Loop
     //
-    loop {
-        command = rlib::get_input();
-        output = world.update_state(&command);
-        rlib::update_screen(output);
+    // Running out of input ends the game the same as quitting
+    input.set_nouns(session.world.nouns_in_scope());
+    while let Some(input_str) = rlib::read_input(input, sink) {
+        let response = session.step(&input_str);
+        rlib::update_screen(sink, response.output)?;
 
-        if matches!(command, rlib::Command::Quit) {
+        if response.game_over {
             break;
         }
+        input.set_nouns(session.world.nouns_in_scope());
     }
 
     //
     // Shutdown and Exit
     //
-    println!("Bye!");
+    session.stop_script()?;
+    sink.write("Bye!\n")?;
+    sink.flush()
 }
@@ -3,66 +3,218 @@
 //
 // A library to support the creation of a text adventure game
 // by Riskpeep
+pub mod analysis;
+pub mod batch;
+pub mod behaviour;
+pub mod dialogue;
+pub mod editor;
+pub mod format;
+pub mod index;
+pub mod lint;
+pub mod map;
+pub mod markup;
+pub mod outcome;
+pub mod script;
+pub mod session;
+pub mod stream;
+pub mod template;
+pub mod transcript;
+
+use behaviour::{Action, Behaviour, SavedBehaviour};
+use dialogue::{Effect, Node, SavedNode, SavedTopic, Topic};
+use index::WorldIndex;
+use outcome::{Change, FailureKind, MessageCategory, Outcome};
 use serde::de::{self, Deserializer, Error, MapAccess, SeqAccess, Visitor};
 use serde::ser::{SerializeStruct, Serializer};
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
 use std::error;
 use std::fmt;
 use std::fs::read_to_string;
-use std::io::{self, Write};
+use std::io;
+use std::ops::Index;
 use std::path::Path;
+use stream::{InputSource, OutputSink};
 
+#[derive(Debug)]
 pub enum Command {
-    Ask(String),
+    AskAbout(String, String),
+    AskFor(String, String),
+    Choose(usize),
     Drop(String),
     Get(String),
     Give(String),
+    GiveTo(String, String),
     Go(String),
     Inventory,
     Look(String),
+    // Whoever the text before a comma names, and the rest as typed. Only
+    // an order if the first part names an actor.
+    Order(String, String),
     Quit,
+    Search(String),
+    Talk(String),
+    Tell(String, String),
     Unknown(String),
 }
 
+impl Command {
+    pub fn noun(&self) -> Option<&str> {
+        match self {
+            Command::Drop(noun)
+            | Command::Get(noun)
+            | Command::Give(noun)
+            | Command::Go(noun)
+            | Command::Look(noun)
+            | Command::Search(noun)
+            | Command::Talk(noun) => Some(noun),
+            // Either noun of these could be the one to be more specific about
+            Command::AskAbout(_, _)
+            | Command::AskFor(_, _)
+            | Command::Choose(_)
+            | Command::GiveTo(_, _)
+            | Command::Inventory
+            | Command::Order(_, _)
+            | Command::Quit
+            | Command::Tell(_, _)
+            | Command::Unknown(_) => None,
+        }
+    }
+}
+
 impl fmt::Display for Command {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Command::Ask(_) => write!(f, "ask"),
+            Command::AskAbout(_, _) => write!(f, "ask"),
+            Command::AskFor(_, _) => write!(f, "ask for"),
+            Command::Choose(_) => write!(f, "choose"),
             Command::Drop(_) => write!(f, "drop"),
             Command::Get(_) => write!(f, "get"),
-            Command::Give(_) => write!(f, "give"),
+            Command::Give(_) | Command::GiveTo(_, _) => write!(f, "give"),
             Command::Go(_) => write!(f, "go"),
             Command::Inventory => write!(f, "inventory"),
             Command::Look(_) => write!(f, "look"),
+            Command::Order(_, _) => write!(f, "order"),
             Command::Quit => write!(f, "quit"),
+            Command::Search(_) => write!(f, "search"),
+            Command::Talk(_) => write!(f, "talk to"),
+            Command::Tell(_, _) => write!(f, "tell"),
             Command::Unknown(_) => write!(f, "unknown"),
         }
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+// Refers to an object in a World. Game files name objects by their id
+// string instead, so that reordering them never changes what refers to what.
+#[derive(PartialOrd, Ord, PartialEq, Eq, Hash, Clone, Copy, Debug)]
+pub struct ObjectId(pub usize);
+
+impl fmt::Display for ObjectId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+// What sort of thing an object is, which decides the fields it must have
+// and what the player can do with it
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Kind {
+    Location,
+    Item,
+    Passage,
+    Actor,
+    Scenery,
+}
+
+impl Kind {
+    // Locations, passages and scenery are never picked up
+    pub fn is_fixed(&self) -> bool {
+        matches!(self, Kind::Location | Kind::Passage | Kind::Scenery)
+    }
+
+    // "an item", "a passage" and so on, for messages
+    pub fn with_article(&self) -> String {
+        match self {
+            Kind::Item | Kind::Actor => format!("an {}", self),
+            Kind::Location | Kind::Passage => format!("a {}", self),
+            Kind::Scenery => self.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Kind::Location => write!(f, "location"),
+            Kind::Item => write!(f, "item"),
+            Kind::Passage => write!(f, "passage"),
+            Kind::Actor => write!(f, "actor"),
+            Kind::Scenery => write!(f, "scenery"),
+        }
+    }
+}
+
+// What finds a hidden object. Searching also finds anything that looking
+// would have.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Copy, Default, Debug)]
+pub enum Reveal {
+    #[default]
+    Search,
+    Examine,
+}
+
+pub fn is_default_reveal(value: &Reveal) -> bool {
+    *value == Reveal::Search
+}
+
+#[derive(Clone, Debug)]
 pub struct Object {
+    pub id: String,
+    pub kind: Kind,
     pub labels: Vec<String>,
     pub description: String,
-    pub location: Option<usize>,
-    pub destination: Option<usize>,
-    pub prospect: Option<usize>,
+    pub location: Option<ObjectId>,
+    pub destination: Option<ObjectId>,
+    pub prospect: Option<ObjectId>,
     pub details: String,
     pub contents: String,
     pub text_go: String,
+    pub fixed: bool,
+    pub refusal: String,
     pub weight: isize,
     pub capacity: isize,
     pub health: isize,
+    pub properties: BTreeMap<String, String>,
+    pub hidden: bool,
+    pub revealed_by: Option<ObjectId>,
+    pub reveal: Reveal,
+    pub text_found: String,
+    // For the passage back that the loader made from a two way passage
+    pub reverse_of: Option<ObjectId>,
+    pub topics: Vec<Topic>,
+    pub dialogue: Vec<Node>,
+    pub behaviours: Vec<Behaviour>,
+    pub obeys: bool,
 }
 
+const DEF_PLAYER: &str = "player";
 const DEF_PROSPECT: &str = "";
 const DEF_DETAILS: &str = "You see nothing special.";
 const DEF_CONTENTS: &str = "You see";
 const DEF_TEXT_GO: &str = "You can't get much closer than this.";
+const DEF_REFUSAL: &str = "That is fixed in place.";
 const DEF_WEIGHT: isize = 99;
 const DEF_CAPACITY: isize = 0;
 const DEF_HEALTH: isize = 0;
 
+pub fn default_player() -> String {
+    DEF_PLAYER.into()
+}
+
+pub fn is_default_player(value: &str) -> bool {
+    value == DEF_PLAYER
+}
+
 pub fn default_prospect() -> String {
     DEF_PROSPECT.into()
 }
@@ -95,6 +247,18 @@ pub fn is_default_text_go(value: &str) -> bool {
     value == DEF_TEXT_GO
 }
 
+pub fn default_refusal() -> String {
+    DEF_REFUSAL.into()
+}
+
+pub fn is_default_refusal(value: &str) -> bool {
+    value == DEF_REFUSAL
+}
+
+pub fn is_false(value: &bool) -> bool {
+    !*value
+}
+
 pub fn default_weight() -> isize {
     DEF_WEIGHT
 }
@@ -139,33 +303,49 @@ pub enum AmbiguousOption<T> {
     Ambiguous,
 }
 
-const LOC_PLAYER: usize = 0;
-// const LOC_BRIDGE: usize = 1;
-// const LOC_GALLEY: usize = 2;
-// const LOC_CRYOCHAMBER: usize = 3;
-// const LOC_OUTSIDE: usize = 4;
-// const LOC_GLOSSY_PHOTO: usize = 5;
-// const LOC_TABLE: usize = 6;
-// const LOC_CRYOSUIT: usize = 7;
-// const LOC_WRINKLED_PHOTO: usize = 8;
-// const LOC_COPILOT: usize = 9;
-// const LOC_PEN: usize = 10;
-// const AFT_TO_GALLEY: usize = 11;
-// const FWD_TO_BRIDGE: usize = 12;
-// const PORT_TO_CRYOCHAMBER: usize = 13;
-// const STBD_TO_GALLEY: usize = 14;
-// const WALL_BRIDGE: usize = 15;
-// const WALL_GALLEY: usize = 16;
-// const WALL_CRYOCHAMBER: usize = 17;
-
+// Front end options that change how the game is presented
 #[derive(Debug)]
+pub struct Settings {
+    pub width: Option<usize>,
+    pub color: bool,
+    pub debug: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            width: None,
+            color: true,
+            debug: false,
+        }
+    }
+}
+
+// The game is won once every goal object is in its goal location
+#[derive(Clone, Debug)]
+pub struct Goal {
+    pub object: ObjectId,
+    pub location: ObjectId,
+}
+
+// Objects can only be changed through the World, which keeps its index
+// in step with them
+#[derive(Clone, Debug)]
 pub struct World {
-    pub objects: Vec<Object>,
+    objects: Vec<Object>,
+    index: WorldIndex,
+    pub player: ObjectId,
+    pub goals: Vec<Goal>,
+    pub turn: u32,
+    // Who the player is talking to and where the dialogue has got to
+    conversation: Option<(ObjectId, usize)>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 #[serde(deny_unknown_fields)]
 pub struct SavedObject {
+    pub id: String,
+    pub kind: Kind,
     pub labels: Vec<String>,
     pub description: String,
     #[serde(default, skip_serializing_if = "String::is_empty")]
@@ -192,6 +372,15 @@ pub struct SavedObject {
         skip_serializing_if = "is_default_text_go"
     )]
     pub text_go: String,
+    // Items can be fixed in place too. Only things fixed in place may say
+    // why they cannot be taken.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub fixed: bool,
+    #[serde(
+        default = "default_refusal",
+        skip_serializing_if = "is_default_refusal"
+    )]
+    pub refusal: String,
     #[serde(default = "default_weight", skip_serializing_if = "is_default_weight")]
     pub weight: isize,
     #[serde(
@@ -201,17 +390,131 @@ pub struct SavedObject {
     pub capacity: isize,
     #[serde(default = "default_health", skip_serializing_if = "is_default_health")]
     pub health: isize,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub properties: BTreeMap<String, String>,
+    // Makes a passage two way. The passage back is added when the game
+    // loads, so the two directions cannot disagree.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reverse: Option<SavedReverse>,
+    // Hidden objects cannot be seen or named until the object that
+    // reveals them is searched, or looked at if reveal is Examine
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub hidden: bool,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub revealed_by: String,
+    #[serde(default, skip_serializing_if = "is_default_reveal")]
+    pub reveal: Reveal,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub text_found: String,
+    // Only actors talk
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub topics: Vec<SavedTopic>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dialogue: Vec<SavedNode>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub behaviours: Vec<SavedBehaviour>,
+    // Whether the actor does as the player tells it
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub obeys: bool,
+}
+
+// Anything left out is made up from the passage it reverses
+#[derive(Serialize, Deserialize, Clone, Default, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct SavedReverse {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub labels: Vec<String>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub description: String,
+    #[serde(
+        default = "default_details",
+        skip_serializing_if = "is_default_details"
+    )]
+    pub details: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub text_go: String,
+}
+
+// Directions that name the way back, each in both orders
+const OPPOSITE_DIRECTIONS: &[(&str, &str)] = &[
+    ("forward", "aft"),
+    ("aft", "forward"),
+    ("port", "starboard"),
+    ("starboard", "port"),
+    ("up", "down"),
+    ("down", "up"),
+    ("north", "south"),
+    ("south", "north"),
+    ("east", "west"),
+    ("west", "east"),
+    ("in", "out"),
+    ("out", "in"),
+];
+
+// Labels that are not directions are kept, so "Aft" and "airlock" reverse
+// to "Forward" and "airlock"
+fn opposite_direction(label: &str) -> String {
+    let lc_label = label.to_lowercase();
+    let Some((_, opposite)) = OPPOSITE_DIRECTIONS
+        .iter()
+        .find(|(direction, _)| *direction == lc_label)
+    else {
+        return label.to_string();
+    };
+
+    if label.starts_with(char::is_uppercase) {
+        let mut chars = opposite.chars();
+        chars
+            .next()
+            .map(|first| first.to_uppercase().chain(chars).collect())
+            .unwrap_or_default()
+    } else {
+        opposite.to_string()
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(deny_unknown_fields)]
+pub struct SavedGoal {
+    pub object: String,
+    pub location: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename = "World", deny_unknown_fields)]
 pub struct SavedWorld {
+    #[serde(default = "default_player", skip_serializing_if = "is_default_player")]
+    pub player: String,
     pub objects: Vec<SavedObject>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub goals: Vec<SavedGoal>,
+    #[serde(default, skip_serializing_if = "is_first_turn")]
+    pub turn: u32,
+}
+
+pub fn is_first_turn(value: &u32) -> bool {
+    *value == 0
 }
 
 #[derive(Debug)]
 pub enum ParseError {
-    UnknownName(String),
+    UnknownName {
+        object: String,
+        field: String,
+        name: String,
+    },
+    EmptyId {
+        object: String,
+    },
+    DuplicateId {
+        id: String,
+    },
+    WrongKind {
+        object: String,
+        problem: String,
+    },
 }
 
 impl error::Error for ParseError {}
@@ -219,11 +522,69 @@ impl error::Error for ParseError {}
 impl fmt::Display for ParseError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            ParseError::UnknownName(message) => write!(f, "{}", message),
+            ParseError::UnknownName {
+                object,
+                field,
+                name,
+            } => write!(f, "'{}' has unknown {} '{}'", object, field, name),
+            ParseError::EmptyId { object } => write!(f, "'{}' has no id", object),
+            ParseError::DuplicateId { id } => {
+                write!(f, "id '{}' is used by more than one object", id)
+            }
+            ParseError::WrongKind { object, problem } => write!(f, "'{}' {}", object, problem),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum LoadError {
+    Io(io::Error),
+    Syntax(ron::error::SpannedError),
+    Semantic(Vec<ParseError>),
+}
+
+impl error::Error for LoadError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            LoadError::Io(io_err) => Some(io_err),
+            LoadError::Syntax(syntax_err) => Some(syntax_err),
+            LoadError::Semantic(_) => None,
         }
     }
 }
 
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LoadError::Io(io_err) => write!(f, "{}", io_err),
+            LoadError::Syntax(syntax_err) => write!(
+                f,
+                "syntax error at line {}, column {}: {}",
+                syntax_err.position.line, syntax_err.position.col, syntax_err.code
+            ),
+            LoadError::Semantic(parse_errs) => {
+                write!(f, "{} error(s) in game file", parse_errs.len())?;
+                for parse_err in parse_errs {
+                    write!(f, "\n    {}", parse_err)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl From<io::Error> for LoadError {
+    fn from(value: io::Error) -> Self {
+        LoadError::Io(value)
+    }
+}
+
+impl From<ron::error::SpannedError> for LoadError {
+    fn from(value: ron::error::SpannedError) -> Self {
+        LoadError::Syntax(value)
+    }
+}
+
 impl Default for World {
     fn default() -> Self {
         Self::new()
@@ -232,64 +593,67 @@ impl Default for World {
 
 impl World {
     pub fn new() -> Self {
-        World { objects: vec![] }
+        World {
+            objects: vec![],
+            index: WorldIndex::default(),
+            player: ObjectId(0),
+            goals: vec![],
+            turn: 0,
+            conversation: None,
+        }
     }
 
-    pub fn read_from_file(game_file: &str) -> Result<World, std::io::Error> {
-        let game_file_path = Path::new(game_file);
-        let game_file_data_res = read_to_string(game_file_path);
+    pub fn len(&self) -> usize {
+        self.objects.len()
+    }
 
-        match game_file_data_res {
-            Ok(game_file_data) => {
-                /*// Create a new World struct
-                let new_world = World::new();
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty()
+    }
 
-                // Write (serialize) the struct to a string using Serde
-                let serialized_ron = ron::to_string(&new_world).unwrap();
+    pub fn iter(&self) -> impl Iterator<Item = (ObjectId, &Object)> {
+        self.objects
+            .iter()
+            .enumerate()
+            .map(|(pos, object)| (ObjectId(pos), object))
+    }
 
-                // Write the serialized string to the console
-                println!("serialized = {}", serialized_ron);
+    pub fn find_id(&self, id: &str) -> Option<ObjectId> {
+        self.index.find_id(id)
+    }
 
-                Ok(new_world)
-                */
+    // Everything directly inside container, in game file order
+    pub fn contents_of(&self, container: ObjectId) -> &[ObjectId] {
+        self.index.children(container)
+    }
 
-                // Read (deserialize) a World struct from the game_file string
-                let deserialized_ron_result: Result<World, ron::error::SpannedError> =
-                    ron::from_str(&game_file_data);
-                match deserialized_ron_result {
-                    Ok(deserialized_ron) => Ok(deserialized_ron),
-                    Err(de_err_str) => Err(std::io::Error::new(
-                        std::io::ErrorKind::Other,
-                        de_err_str.to_string(),
-                    )),
-                }
-            }
-            Err(file_err) => Err(file_err),
-        }
+    // The one place an object's location changes
+    fn set_location(&mut self, object: ObjectId, to: Option<ObjectId>) {
+        let from = self.objects[object.0].location;
+        self.objects[object.0].location = to;
+        self.index.moved(object, from, to);
     }
 
-    fn object_has_label(&self, object: &Object, noun: &str) -> bool {
-        let mut result: bool = false;
-        for (_, label) in object.labels.iter().enumerate() {
-            if label.to_lowercase() == noun {
-                result = true;
-                break;
-            }
-        }
-        result
+    pub fn read_from_file(game_file: &str) -> Result<World, LoadError> {
+        // Syntax and naming problems are reported separately, so read
+        // the file as a SavedWorld before resolving names into a World
+        SavedWorld::read_from_file(game_file)?.try_into()
+    }
+
+    // Fills in any placeholders in game text from the current state
+    pub fn render(&self, text: &str) -> String {
+        template::render(text, self)
     }
 
     fn get_object_index(
         &self,
         noun: &str,
-        from: Option<usize>,
+        from: Option<ObjectId>,
         max_distance: Distance,
-    ) -> AmbiguousOption<usize> {
-        let mut result: AmbiguousOption<usize> = AmbiguousOption::None;
-        for (pos, object) in self.objects.iter().enumerate() {
-            if self.object_has_label(object, noun)
-                && self.get_distance(from, Some(pos)) <= max_distance
-            {
+    ) -> AmbiguousOption<ObjectId> {
+        let mut result: AmbiguousOption<ObjectId> = AmbiguousOption::None;
+        for &pos in self.index.with_label(noun) {
+            if !self[pos].hidden && self.get_distance(from, Some(pos)) <= max_distance {
                 if result == AmbiguousOption::None {
                     result = AmbiguousOption::Some(pos);
                 } else {
@@ -300,16 +664,65 @@ impl World {
         result
     }
 
-    pub fn is_holding(&self, container: Option<usize>, object: Option<usize>) -> bool {
-        object.is_some() && (object.and_then(|a| self.objects[a].location) == container)
+    pub fn is_won(&self) -> bool {
+        !self.goals.is_empty() && self.goals_met() == self.goals.len()
+    }
+
+    pub fn goals_met(&self) -> usize {
+        self.goals
+            .iter()
+            .filter(|goal| self.is_holding(Some(goal.location), Some(goal.object)))
+            .count()
+    }
+
+    pub fn player_location(&self) -> Option<ObjectId> {
+        self[self.player].location
+    }
+
+    // Every label of everything the player can see from where they are
+    pub fn nouns_in_scope(&self) -> Vec<String> {
+        // Only the player, where they are, what both hold and where the
+        // passages there lead can be in scope
+        let near: Vec<ObjectId> = [Some(self.player), self.player_location()]
+            .into_iter()
+            .flatten()
+            .collect();
+        let mut candidates: Vec<ObjectId> = near.clone();
+        for &container in &near {
+            for &pos in self.contents_of(container) {
+                candidates.push(pos);
+                candidates.extend(self.contents_of(pos));
+                candidates.extend(self[pos].prospect);
+            }
+        }
+
+        let mut nouns: Vec<String> = Vec::new();
+        for pos in candidates {
+            if !self[pos].hidden
+                && self.get_distance(Some(self.player), Some(pos)) <= Distance::OverThere
+            {
+                nouns.extend(self[pos].labels.iter().map(|label| label.to_lowercase()));
+            }
+        }
+        nouns.sort();
+        nouns.dedup();
+        nouns
     }
 
-    fn get_passage_index(&self, from_opt: Option<usize>, to_opt: Option<usize>) -> Option<usize> {
-        let mut result: Option<usize> = None;
+    pub fn is_holding(&self, container: Option<ObjectId>, object: Option<ObjectId>) -> bool {
+        object.is_some() && (object.and_then(|a| self[a].location) == container)
+    }
 
-        if from_opt.is_some() && to_opt.is_some() {
-            for (pos, object) in self.objects.iter().enumerate() {
-                if self.is_holding(from_opt, Some(pos)) && object.prospect == to_opt {
+    fn get_passage_index(
+        &self,
+        from_opt: Option<ObjectId>,
+        to_opt: Option<ObjectId>,
+    ) -> Option<ObjectId> {
+        let mut result: Option<ObjectId> = None;
+
+        if let (Some(from_idx), Some(_)) = (from_opt, to_opt) {
+            for &pos in self.contents_of(from_idx) {
+                if !self[pos].hidden && self[pos].prospect == to_opt {
                     result = Some(pos);
                     break;
                 }
@@ -320,9 +733,9 @@ impl World {
         }
     }
 
-    pub fn get_distance(&self, from: Option<usize>, to: Option<usize>) -> Distance {
-        let from_loc = from.and_then(|a| self.objects[a].location);
-        let to_loc = to.and_then(|a| self.objects[a].location);
+    pub fn get_distance(&self, from: Option<ObjectId>, to: Option<ObjectId>) -> Distance {
+        let from_loc = from.and_then(|a| self[a].location);
+        let to_loc = to.and_then(|a| self[a].location);
 
         if to.is_none() {
             Distance::UnknownObject
@@ -345,346 +758,1084 @@ impl World {
         }
     }
 
-    fn get_visible(&self, message: &str, noun: &str) -> (String, Option<usize>) {
-        let obj_over_there = self.get_object_index(noun, Some(LOC_PLAYER), Distance::OverThere);
-        let obj_not_here = self.get_object_index(noun, Some(LOC_PLAYER), Distance::NotHere);
+    fn get_visible(&self, message: &str, noun: &str) -> Result<ObjectId, Outcome> {
+        let obj_over_there = self.get_object_index(noun, Some(self.player), Distance::OverThere);
+        let obj_not_here = self.get_object_index(noun, Some(self.player), Distance::NotHere);
 
         match (obj_over_there, obj_not_here) {
-            (AmbiguousOption::None, AmbiguousOption::None) => {
-                (format!("I don't understand {}.\n", message), None)
-            }
-            (AmbiguousOption::None, AmbiguousOption::Some(_)) => {
-                (format!("You don't see any '{}' here.\n", noun), None)
-            }
+            (AmbiguousOption::None, AmbiguousOption::None) => Err(Outcome::failure(
+                FailureKind::UnknownObject,
+                format!("I don't understand {}.", message),
+            )),
+            (AmbiguousOption::None, AmbiguousOption::Some(_)) => Err(Outcome::failure(
+                FailureKind::NotHere,
+                format!("You don't see any '{}' here.", noun),
+            )),
             (AmbiguousOption::Ambiguous, _)
-            | (AmbiguousOption::None, AmbiguousOption::Ambiguous) => (
-                format!("Please be more specific about which {} you mean.\n", noun),
-                None,
-            ),
-            (AmbiguousOption::Some(index), _) => (String::new(), Some(index)),
+            | (AmbiguousOption::None, AmbiguousOption::Ambiguous) => Err(Outcome::failure(
+                FailureKind::Ambiguous,
+                format!("Please be more specific about which {} you mean.", noun),
+            )),
+            (AmbiguousOption::Some(index), _) => Ok(index),
         }
     }
 
     pub fn get_possession(
         &mut self,
-        from: Option<usize>,
+        from: Option<ObjectId>,
         command: Command,
         noun: &str,
-    ) -> (String, Option<usize>) {
+    ) -> Result<ObjectId, Outcome> {
         let object_held = self.get_object_index(noun, from, Distance::HeldContained);
         let object_not_here = self.get_object_index(noun, from, Distance::NotHere);
 
         match (from, object_held, object_not_here) {
-            (None, _, _) => (
-                format!("I don't understand what you want to {}.\n", command),
-                None,
-            ),
-            (Some(_), AmbiguousOption::None, AmbiguousOption::None) => (
-                format!("I don't understand what you want to {}.\n", command),
-                None,
-            ),
-            (Some(from_idx), AmbiguousOption::None, _) if from_idx == LOC_PLAYER => {
-                (format!("You are not holding any {}.\n", noun), None)
+            (None, _, _) => Err(Outcome::failure(
+                FailureKind::NoActor,
+                format!("I don't understand what you want to {}.", command),
+            )),
+            (Some(_), AmbiguousOption::None, AmbiguousOption::None) => Err(Outcome::failure(
+                FailureKind::UnknownObject,
+                format!("I don't understand what you want to {}.", command),
+            )),
+            (Some(from_idx), AmbiguousOption::None, _) if from_idx == self.player => {
+                Err(Outcome::failure(
+                    FailureKind::NotHeld,
+                    format!("You are not holding any {}.", noun),
+                ))
             }
-            (Some(from_idx), AmbiguousOption::None, _) => (
+            (Some(from_idx), AmbiguousOption::None, _) => Err(Outcome::failure(
+                FailureKind::NotHeld,
                 format!(
-                    "There appears to be no {} you can get from {}.\n",
-                    noun, self.objects[from_idx].labels[0]
+                    "There appears to be no {} you can get from {}.",
+                    noun, self[from_idx].labels[0]
                 ),
-                None,
-            ),
+            )),
             (Some(from_idx), AmbiguousOption::Some(object_held_idx), _)
                 if object_held_idx == from_idx =>
             {
-                (
+                Err(Outcome::failure(
+                    FailureKind::NotAllowed,
                     format!(
-                        "You should not be doing that to {}.\n",
-                        self.objects[object_held_idx].labels[0]
+                        "You should not be doing that to {}.",
+                        self[object_held_idx].labels[0]
                     ),
-                    None,
-                )
+                ))
             }
-            (Some(_), AmbiguousOption::Ambiguous, _) => (
+            (Some(_), AmbiguousOption::Ambiguous, _) => Err(Outcome::failure(
+                FailureKind::Ambiguous,
                 format!(
-                    "Please be more specific about which {} you want to {}.\n",
+                    "Please be more specific about which {} you want to {}.",
                     noun, command
                 ),
-                None,
-            ),
-            (Some(_), AmbiguousOption::Some(object_held_idx), _) => {
-                ("".to_string(), Some(object_held_idx))
-            }
+            )),
+            (Some(_), AmbiguousOption::Some(object_held_idx), _) => Ok(object_held_idx),
         }
     }
 
-    pub fn actor_here(&self) -> Option<usize> {
-        let mut actor_loc: Option<usize> = None;
+    // Whether the noun means an actor the player could know about
+    pub fn names_actor(&self, noun: &str) -> bool {
+        self.index
+            .with_label(noun)
+            .iter()
+            .any(|&pos| self[pos].kind == Kind::Actor && !self[pos].hidden)
+    }
+
+    // Every label of every object that the noun could mean. Hidden objects
+    // cannot be named until they are found.
+    pub fn labels_meant_by(&self, noun: &str) -> Vec<String> {
+        self.index
+            .with_label(noun)
+            .iter()
+            .filter(|&&pos| !self[pos].hidden)
+            .flat_map(|&pos| self[pos].labels.iter().map(|label| label.to_lowercase()))
+            .collect()
+    }
 
-        for (pos, object) in self.objects.iter().enumerate() {
-            if self.is_holding(self.objects[LOC_PLAYER].location, Some(pos))
-                && pos == LOC_PLAYER
-                && object.health > 0
-            {
-                actor_loc = Some(pos);
-            }
-        }
-        actor_loc
+    // Everyone but the player where the player is, in game file order
+    pub fn actors_here(&self) -> Vec<ObjectId> {
+        let Some(player_loc) = self[self.player].location else {
+            return Vec::new();
+        };
+        self.contents_of(player_loc)
+            .iter()
+            .copied()
+            .filter(|&pos| pos != self.player && self[pos].kind == Kind::Actor && !self[pos].hidden)
+            .collect()
     }
 
-    pub fn list_objects_at_location(&self, location: usize) -> (String, i32) {
+    pub fn list_objects_at_location(&self, location: ObjectId) -> (String, i32) {
         let mut output = String::new();
         let mut count: i32 = 0;
-        for (pos, object) in self.objects.iter().enumerate() {
-            if pos != LOC_PLAYER && self.is_holding(Some(location), Some(pos)) {
+        for &pos in self.contents_of(location) {
+            if pos != self.player && !self[pos].hidden {
                 if count == 0 {
-                    output = output + &format!("{}:\n", self.objects[location].contents);
+                    output = output + &format!("{}:\n", self.render(&self[location].contents));
                 }
                 count += 1;
-                output = output + &format!("{}\n", object.description);
+                output = output + &format!("{}\n", self.render(&self[pos].description));
             }
         }
         (output, count)
     }
 
-    fn weight_of_contents(&self, container: usize) -> isize {
-        let mut sum: isize = 0;
-        for (pos, object) in self.objects.iter().enumerate() {
-            if self.is_holding(Some(container), Some(pos)) {
-                sum += object.weight;
-            }
-        }
-        sum
+    fn weight_of_contents(&self, container: ObjectId) -> isize {
+        self.contents_of(container)
+            .iter()
+            .map(|&pos| self[pos].weight)
+            .sum()
     }
 
-    pub fn describe_move(&self, obj_opt: Option<usize>, to: Option<usize>) -> String {
-        let obj_loc = obj_opt.and_then(|a| self.objects[a].location);
-        let player_loc = self.objects[LOC_PLAYER].location;
+    pub fn describe_move(&self, obj_opt: Option<ObjectId>, to: Option<ObjectId>) -> String {
+        let obj_loc = obj_opt.and_then(|a| self[a].location);
+        let player_loc = self[self.player].location;
 
         match (obj_opt, obj_loc, to, player_loc) {
             (Some(obj_opt_idx), _, Some(to_idx), Some(player_loc_idx))
                 if to_idx == player_loc_idx =>
             {
-                format!("You drop {}.\n", self.objects[obj_opt_idx].labels[0])
+                format!("You drop {}.", self[obj_opt_idx].labels[0])
             }
-            (Some(obj_opt_idx), _, Some(to_idx), _) if to_idx != LOC_PLAYER => {
-                if self.objects[to_idx].health > 0 {
+            (Some(obj_opt_idx), _, Some(to_idx), _) if to_idx != self.player => {
+                if self[to_idx].kind == Kind::Actor {
                     format!(
-                        "You give {} to {}.\n",
-                        self.objects[obj_opt_idx].labels[0], self.objects[to_idx].labels[0]
+                        "You give {} to {}.",
+                        self[obj_opt_idx].labels[0], self[to_idx].labels[0]
                     )
                 } else {
                     format!(
-                        "You put {} in {}.\n",
-                        self.objects[obj_opt_idx].labels[0], self.objects[to_idx].labels[0]
+                        "You put {} in {}.",
+                        self[obj_opt_idx].labels[0], self[to_idx].labels[0]
                     )
                 }
             }
             (Some(obj_opt_idx), Some(obj_loc_idx), _, Some(player_loc_idx))
                 if obj_loc_idx == player_loc_idx =>
             {
-                format!("You pick up {}.\n", self.objects[obj_opt_idx].labels[0])
+                format!("You pick up {}.", self[obj_opt_idx].labels[0])
             }
             (Some(obj_opt_idx), Some(obj_loc_idx), _, _) => {
                 format!(
-                    "You get {} from {}.\n",
-                    self.objects[obj_opt_idx].labels[0], self.objects[obj_loc_idx].labels[0]
+                    "You get {} from {}.",
+                    self[obj_opt_idx].labels[0], self[obj_loc_idx].labels[0]
                 )
             }
             // This arm should never get hit.
-            (None, _, _, _) | (_, None, _, _) => "How can you drop nothing?.\n".to_string(),
+            (None, _, _, _) | (_, None, _, _) => "How can you drop nothing?.".to_string(),
         }
     }
 
-    pub fn move_object(&mut self, obj_opt: Option<usize>, to: Option<usize>) -> String {
-        let obj_loc = obj_opt.and_then(|a| self.objects[a].location);
+    pub fn move_object(&mut self, obj_opt: Option<ObjectId>, to: Option<ObjectId>) -> Outcome {
+        let obj_loc = obj_opt.and_then(|a| self[a].location);
 
         match (obj_opt, obj_loc, to) {
-            (None, _, _) => String::new(),
-            (Some(_), _, None) => "There is nobody to give that to.\n".to_string(),
-            (Some(_), None, Some(_)) => "That is way too heavy.\n".to_string(),
+            (None, _, _) => {
+                Outcome::failure(FailureKind::UnknownObject, "How can you move nothing?")
+            }
+            (Some(_), _, None) => {
+                Outcome::failure(FailureKind::NoActor, "There is nobody to give that to.")
+            }
+            (Some(_), None, Some(_)) => {
+                Outcome::failure(FailureKind::TooHeavy, "That is way too heavy.")
+            }
             (Some(obj_idx), Some(_), Some(to_idx))
-                if self.objects[obj_idx].weight > self.objects[to_idx].capacity =>
+                if self[obj_idx].weight > self[to_idx].capacity =>
             {
-                "That is way too heavy.\n".to_string()
+                Outcome::failure(FailureKind::TooHeavy, "That is way too heavy.")
             }
             (Some(obj_idx), Some(_), Some(to_idx))
-                if self.objects[obj_idx].weight + self.weight_of_contents(to_idx)
-                    > self.objects[to_idx].capacity =>
+                if self[obj_idx].weight + self.weight_of_contents(to_idx)
+                    > self[to_idx].capacity =>
             {
-                "That would become to heavy.\n".to_string()
+                Outcome::failure(FailureKind::TooHeavy, "That would become to heavy.")
             }
-            (Some(obj_idx), Some(_), Some(to_idx)) => {
+            (Some(obj_idx), Some(obj_loc_idx), Some(to_idx)) => {
                 let output = self.describe_move(obj_opt, to);
-                self.objects[obj_idx].location = Some(to_idx);
-                output
+                self.set_location(obj_idx, Some(to_idx));
+                Outcome::success()
+                    .with(MessageCategory::Narration, output)
+                    .with_change(Change::ObjectMoved {
+                        object: obj_idx,
+                        from: Some(obj_loc_idx),
+                        to: to_idx,
+                    })
+            }
+        }
+    }
+
+    // The actor the player is talking to, and the dialogue node reached
+    pub fn conversation(&self) -> Option<(ObjectId, usize)> {
+        self.conversation
+    }
+
+    pub fn update_state(&mut self, command: &Command) -> Outcome {
+        let outcome = self.do_command(command);
+        if outcome.turn_consumed {
+            self.turn += 1;
+            let actions = self.run_actors(&outcome.changes);
+            return outcome.then(actions);
+        }
+        outcome
+    }
+
+    // Whether the player would see something happen to object
+    fn in_view(&self, object: ObjectId) -> bool {
+        matches!(
+            self.get_distance(Some(self.player), Some(object)),
+            Distance::Here | Distance::HereContained
+        )
+    }
+
+    // Actors act once the player's turn is over, in game file order. The
+    // player is only told what they can see, before or after it happens.
+    fn run_actors(&mut self, changes: &[Change]) -> Outcome {
+        // Going nowhere, as through the airlock, is not arriving anywhere
+        let player_moved = changes.iter().find_map(|change| match change {
+            Change::PlayerMoved { from, to } if *from != Some(*to) => Some((*from, *to)),
+            _ => None,
+        });
+        let actors: Vec<ObjectId> = self
+            .index
+            .actors()
+            .iter()
+            .copied()
+            .filter(|&pos| pos != self.player && !self[pos].hidden)
+            .collect();
+
+        let mut outcome = Outcome::success();
+        for actor in actors {
+            // An actor that was told to go somewhere has had its move
+            let mut moved = changes.iter().any(
+                |change| matches!(change, Change::ObjectMoved { object, .. } if *object == actor),
+            );
+            for pos in 0..self[actor].behaviours.len() {
+                let behaviour = self[actor].behaviours[pos].clone();
+                if !template::condition_holds(&behaviour.when, self) {
+                    continue;
+                }
+
+                let seen_before = self.in_view(actor);
+                let Some((text, change)) = self.act(actor, pos, player_moved, &mut moved) else {
+                    continue;
+                };
+                if let Some(change) = change {
+                    outcome = outcome.with_change(change);
+                }
+                if seen_before || self.in_view(actor) {
+                    let text = match behaviour.text.is_empty() {
+                        true => text,
+                        false => self.render(&behaviour.text),
+                    };
+                    outcome = outcome.with(MessageCategory::Narration, text);
+                }
+            }
+        }
+        outcome
+    }
+
+    // Moves object for an actor's behaviour, whatever it weighs
+    fn act_move(&mut self, object: ObjectId, to: ObjectId) -> Change {
+        let from = self[object].location;
+        self.set_location(object, Some(to));
+        Change::ObjectMoved { object, from, to }
+    }
+
+    // Carries out one of an actor's behaviours, if it applies, and says
+    // what the player would see
+    fn act(
+        &mut self,
+        actor: ObjectId,
+        pos: usize,
+        player_moved: Option<(Option<ObjectId>, ObjectId)>,
+        moved: &mut bool,
+    ) -> Option<(String, Option<Change>)> {
+        let name = self[actor].labels[0].to_string();
+        let here = self[actor].location;
+
+        match self[actor].behaviours[pos].action.clone() {
+            Action::Patrol { route, next } if !*moved => {
+                // Carry on from wherever the actor has got to on its route
+                let step = (0..route.len())
+                    .map(|offset| (next + offset) % route.len())
+                    .find(|&step| here.is_some() && self[route[step]].location == here)?;
+                let passage = route[step];
+                let to = self[passage].destination?;
+
+                let seen_leaving = self.in_view(actor);
+                let next = (step + 1) % route.len();
+                self.objects[actor.0].behaviours[pos].action = Action::Patrol { route, next };
+                let change = Some(self.act_move(actor, to));
+                *moved = true;
+                let text = match seen_leaving {
+                    true => format!("{} goes {}.", name, self[passage].labels[0].to_lowercase()),
+                    false => format!("{} arrives.", name),
+                };
+                Some((text, change))
+            }
+            Action::Follow if !*moved => {
+                let (from, to) = player_moved?;
+                if from.is_none() || here != from {
+                    return None;
+                }
+                let change = Some(self.act_move(actor, to));
+                *moved = true;
+                Some((format!("{} follows you.", name), change))
+            }
+            Action::Greet => {
+                let (_, to) = player_moved?;
+                if here != Some(to) {
+                    return None;
+                }
+                Some((format!("{} looks up as you come in.", name), None))
+            }
+            Action::Take(object) => {
+                if here.is_none()
+                    || self[object].location != here
+                    || self[object].hidden
+                    || self[object].is_fixed()
+                    || self[object].weight + self.weight_of_contents(actor) > self[actor].capacity
+                {
+                    return None;
+                }
+                let change = Some(self.act_move(object, actor));
+                Some((
+                    format!("{} picks up {}.", name, self[object].labels[0]),
+                    change,
+                ))
             }
+            Action::Drop(object) => {
+                let here = here?;
+                if self[object].location != Some(actor) {
+                    return None;
+                }
+                let change = Some(self.act_move(object, here));
+                Some((
+                    format!("{} drops {}.", name, self[object].labels[0]),
+                    change,
+                ))
+            }
+            Action::Patrol { .. } | Action::Follow => None,
         }
     }
 
-    pub fn update_state(&mut self, command: &Command) -> String {
+    fn do_command(&mut self, command: &Command) -> Outcome {
+        // Doing anything else walks away from a conversation
+        if !matches!(command, Command::Choose(_)) {
+            self.conversation = None;
+        }
+
         match command {
-            Command::Ask(noun) => self.do_ask(noun),
+            Command::AskAbout(actor, topic) => self.do_topic(actor, topic, false),
+            Command::AskFor(actor, noun) => self.do_ask_for(actor, noun),
+            Command::Choose(choice) => self.do_choose(*choice),
             Command::Drop(noun) => self.do_drop(noun),
             Command::Get(noun) => self.do_get(noun),
             Command::Give(noun) => self.do_give(noun),
+            Command::GiveTo(noun, actor) => self.do_give_to(noun, actor),
             Command::Go(noun) => self.do_go(noun),
             Command::Inventory => self.do_inventory(),
             Command::Look(noun) => self.do_look(noun),
-            Command::Quit => "Quitting.\nThank you for playing!".to_string(),
-            Command::Unknown(input_str) => format!("I don't know how to '{}'.", input_str),
+            Command::Order(actor, order) if self.names_actor(actor) => {
+                self.do_order(actor, &parse_command(order.to_string()))
+            }
+            Command::Order(actor, order) => {
+                self.do_command(&parse_command(format!("{}, {}", actor, order)))
+            }
+            Command::Search(noun) => self.do_search(noun),
+            Command::Talk(actor) => self.do_talk(actor),
+            Command::Tell(actor, topic) => self.do_topic(actor, topic, true),
+            Command::Quit => Outcome::success()
+                .with(MessageCategory::System, "Quitting.\nThank you for playing!")
+                .without_turn(),
+            Command::Unknown(input_str) => Outcome::failure(
+                FailureKind::UnknownCommand,
+                format!("I don't know how to '{}'.", input_str),
+            ),
         }
     }
 
-    pub fn do_ask(&mut self, noun: &str) -> String {
-        let actor_loc = self.actor_here();
-        let (output, object_idx) =
-            self.get_possession(actor_loc, Command::Ask("ask".to_string()), noun);
+    // Someone here the player can deal with, named by noun. verb says what
+    // the player wants to do with them.
+    fn get_actor(&self, verb: &str, noun: &str) -> Result<ObjectId, Outcome> {
+        let actor_idx = self.get_visible(&format!("who you want to {}", verb), noun)?;
+
+        match self.get_distance(Some(self.player), Some(actor_idx)) {
+            Distance::Me => Err(Outcome::failure(
+                FailureKind::NotAllowed,
+                "Talking to yourself will not help.",
+            )),
+            Distance::OverThere => Err(Outcome::failure(
+                FailureKind::TooFar,
+                "Too far away, move closer please.",
+            )),
+            _ if self[actor_idx].kind != Kind::Actor => Err(Outcome::failure(
+                FailureKind::NotAllowed,
+                format!("You can't {} {}.", verb, self[actor_idx].labels[0]),
+            )),
+            _ => Ok(actor_idx),
+        }
+    }
+
+    pub fn do_ask_for(&mut self, actor: &str, noun: &str) -> Outcome {
+        let actor_idx = match self.get_actor("ask", actor) {
+            Ok(actor_idx) => actor_idx,
+            Err(outcome) => return outcome,
+        };
+        let command = Command::AskFor(actor.to_string(), noun.to_string());
+        match self.get_possession(Some(actor_idx), command, noun) {
+            Ok(object_idx) => self.move_object(Some(object_idx), Some(self.player)),
+            Err(outcome) => outcome,
+        }
+    }
+
+    // Effects move things whatever they weigh, as the game file says
+    fn apply_effects(&mut self, effects: &[Effect]) -> Outcome {
+        let mut outcome = Outcome::success();
+        for effect in effects {
+            match effect {
+                Effect::Move { object, to } => {
+                    let from = self[*object].location;
+                    self.set_location(*object, Some(*to));
+                    outcome = outcome.with_change(Change::ObjectMoved {
+                        object: *object,
+                        from,
+                        to: *to,
+                    });
+                }
+                Effect::Set {
+                    object,
+                    property,
+                    value,
+                } => {
+                    self.objects[object.0]
+                        .properties
+                        .insert(property.to_string(), value.to_string());
+                    outcome = outcome.with_change(Change::PropertyChanged { object: *object });
+                }
+                Effect::Reveal { object } if self[*object].hidden => {
+                    self.objects[object.0].hidden = false;
+                    outcome = outcome.with_change(Change::ObjectRevealed { object: *object });
+                }
+                Effect::Reveal { .. } => (),
+            }
+        }
+        outcome
+    }
+
+    // The first topic about this that has something to say
+    fn do_topic(&mut self, actor: &str, topic: &str, telling: bool) -> Outcome {
+        let verb = if telling { "tell" } else { "ask" };
+        let actor_idx = match self.get_actor(verb, actor) {
+            Ok(actor_idx) => actor_idx,
+            Err(outcome) => return outcome,
+        };
+        let name = self[actor_idx].labels[0].to_string();
+        if topic.is_empty() {
+            return Outcome::failure(
+                FailureKind::UnknownObject,
+                format!("What do you want to {} {} about?", verb, name),
+            );
+        }
 
-        output + self.move_object(object_idx, Some(LOC_PLAYER)).as_str()
+        let found = self[actor_idx]
+            .topics
+            .iter()
+            .find(|found| {
+                let text = if telling { &found.tell } else { &found.ask };
+                found.is_about(topic)
+                    && !text.is_empty()
+                    && template::condition_holds(&found.when, self)
+            })
+            .cloned();
+
+        match found {
+            Some(found) => {
+                let text = if telling { &found.tell } else { &found.ask };
+                Outcome::success()
+                    .with(MessageCategory::Narration, self.render(text))
+                    .then(self.apply_effects(&found.effects))
+            }
+            None if telling => Outcome::success().with(
+                MessageCategory::Narration,
+                format!("{} shows no interest in {}.", name, topic),
+            ),
+            None => Outcome::success().with(
+                MessageCategory::Narration,
+                format!("{} has nothing to say about {}.", name, topic),
+            ),
+        }
     }
 
-    pub fn do_drop(&mut self, noun: &str) -> String {
-        let (output, object_idx) =
-            self.get_possession(Some(LOC_PLAYER), Command::Drop("drop".to_string()), noun);
-        let player_loc = self.objects[LOC_PLAYER].location;
+    // Says what the node says, then offers its choices. A node with no
+    // choices left ends the conversation.
+    fn enter_node(&mut self, actor_idx: ObjectId, node_idx: usize) -> Outcome {
+        let node = self[actor_idx].dialogue[node_idx].clone();
+        if !template::condition_holds(&node.when, self) {
+            self.conversation = None;
+            return Outcome::success().with(
+                MessageCategory::Narration,
+                format!("{} has nothing more to say.", self[actor_idx].labels[0]),
+            );
+        }
 
-        output + self.move_object(object_idx, player_loc).as_str()
+        let mut outcome = Outcome::success()
+            .with(MessageCategory::Narration, self.render(&node.text))
+            .then(self.apply_effects(&node.effects));
+
+        let choices: Vec<String> = node
+            .choices
+            .iter()
+            .filter(|choice| template::condition_holds(&choice.when, self))
+            .enumerate()
+            .map(|(pos, choice)| format!("{}. {}", pos + 1, self.render(&choice.text)))
+            .collect();
+        if choices.is_empty() {
+            self.conversation = None;
+        } else {
+            self.conversation = Some((actor_idx, node_idx));
+            outcome = outcome.with(MessageCategory::ObjectList, choices.join("\n"));
+        }
+        outcome
     }
 
-    pub fn do_get(&mut self, noun: &str) -> String {
-        let (output_vis, obj_opt) = self.get_visible("what you want to get", noun);
+    // Conversations start at the first node whose condition holds
+    pub fn do_talk(&mut self, actor: &str) -> Outcome {
+        let actor_idx = match self.get_actor("talk to", actor) {
+            Ok(actor_idx) => actor_idx,
+            Err(outcome) => return outcome,
+        };
 
-        let player_to_obj = self.get_distance(Some(LOC_PLAYER), obj_opt);
+        let start = self[actor_idx]
+            .dialogue
+            .iter()
+            .position(|node| template::condition_holds(&node.when, self));
+        match start {
+            Some(node_idx) => self.enter_node(actor_idx, node_idx),
+            None => Outcome::success().with(
+                MessageCategory::Narration,
+                format!("{} has nothing to say.", self[actor_idx].labels[0]),
+            ),
+        }
+    }
 
-        match (player_to_obj, obj_opt) {
-            (Distance::Me, _) => output_vis + "You should not be doing that to yourself.\n",
-            (Distance::Held, Some(object_idx)) => {
-                output_vis
-                    + &format!(
-                        "You already have {}.\n",
-                        self.objects[object_idx].description
-                    )
+    // Choices are numbered from 1, counting only those on offer
+    pub fn do_choose(&mut self, choice: usize) -> Outcome {
+        let Some((actor_idx, node_idx)) = self.conversation else {
+            return Outcome::failure(FailureKind::NoActor, "You are not talking to anyone.");
+        };
+        if self.get_distance(Some(self.player), Some(actor_idx)) != Distance::Here {
+            self.conversation = None;
+            return Outcome::failure(
+                FailureKind::NoActor,
+                format!("{} is no longer here.", self[actor_idx].labels[0]),
+            );
+        }
+
+        let choices: Vec<_> = self[actor_idx].dialogue[node_idx]
+            .choices
+            .iter()
+            .filter(|choice| template::condition_holds(&choice.when, self))
+            .cloned()
+            .collect();
+        let Some(chosen) = choice.checked_sub(1).and_then(|pos| choices.get(pos)) else {
+            return Outcome::failure(
+                FailureKind::NotAllowed,
+                format!("Please choose a number from 1 to {}.", choices.len()),
+            );
+        };
+
+        let outcome = Outcome::success().with(
+            MessageCategory::Narration,
+            format!("You say, \"{}\"", self.render(&chosen.text)),
+        );
+        match chosen.next {
+            Some(next) => outcome.then(self.enter_node(actor_idx, next)),
+            None => {
+                self.conversation = None;
+                outcome
+            }
+        }
+    }
+
+    pub fn do_drop(&mut self, noun: &str) -> Outcome {
+        let player_loc = self[self.player].location;
+        match self.get_possession(Some(self.player), Command::Drop("drop".to_string()), noun) {
+            Ok(object_idx) => self.move_object(Some(object_idx), player_loc),
+            Err(outcome) => outcome,
+        }
+    }
+
+    pub fn do_get(&mut self, noun: &str) -> Outcome {
+        if noun == "all" {
+            return self.do_get_all();
+        }
+
+        let obj_idx = match self.get_visible("what you want to get", noun) {
+            Ok(obj_idx) => obj_idx,
+            Err(outcome) => return outcome,
+        };
+
+        match self.get_distance(Some(self.player), Some(obj_idx)) {
+            Distance::Me => Outcome::failure(
+                FailureKind::NotAllowed,
+                "You should not be doing that to yourself.",
+            ),
+            Distance::Held => Outcome::failure(
+                FailureKind::AlreadyDone,
+                format!(
+                    "You already have {}.",
+                    self.render(&self[obj_idx].description)
+                ),
+            ),
+            Distance::OverThere => {
+                Outcome::failure(FailureKind::TooFar, "Too far away, move closer please.")
+            }
+            _ if self[obj_idx].is_fixed() => {
+                Outcome::failure(FailureKind::NotAllowed, self.render(&self[obj_idx].refusal))
             }
-            (Distance::OverThere, _) => output_vis + "Too far away, move closer please.\n",
-            (Distance::UnknownObject, _) => output_vis,
             _ => {
-                let obj_loc = obj_opt.and_then(|a| self.objects[a].location);
-
-                if obj_loc.is_some() && self.objects[obj_loc.unwrap()].health > 0 {
-                    output_vis
-                        + &format!(
-                            "You should ask {} nicely.\n",
-                            self.objects[obj_loc.unwrap()].labels[0]
-                        )
-                } else {
-                    self.move_object(obj_opt, Some(LOC_PLAYER))
+                let obj_loc = self[obj_idx].location;
+
+                match obj_loc {
+                    Some(obj_loc_idx) if self[obj_loc_idx].kind == Kind::Actor => Outcome::failure(
+                        FailureKind::NotAllowed,
+                        format!("You should ask {} nicely.", self[obj_loc_idx].labels[0]),
+                    ),
+                    _ => self.move_object(Some(obj_idx), Some(self.player)),
+                }
+            }
+        }
+    }
+
+    // Picks up every item lying here that is not fixed in place. Items that
+    // cannot be carried are reported, but do not spoil the rest.
+    fn do_get_all(&mut self) -> Outcome {
+        let Some(player_loc) = self[self.player].location else {
+            return Outcome::failure(FailureKind::NotHere, "There is nothing here you can take.");
+        };
+        let items: Vec<ObjectId> = self
+            .contents_of(player_loc)
+            .iter()
+            .copied()
+            .filter(|&pos| {
+                self[pos].kind == Kind::Item && !self[pos].is_fixed() && !self[pos].hidden
+            })
+            .collect();
+
+        let mut taken: Option<Outcome> = None;
+        let mut refused: Option<Outcome> = None;
+        for item in items {
+            let mut outcome = self.move_object(Some(item), Some(self.player));
+            if outcome.is_success() {
+                taken = Some(match taken {
+                    Some(earlier) => earlier.then(outcome),
+                    None => outcome,
+                });
+                continue;
+            }
+
+            // Say which item each refusal is about
+            for message in outcome.messages.iter_mut() {
+                message.text = format!("{}: {}", self[item].labels[0], message.text);
+            }
+            refused = Some(match refused {
+                Some(earlier) => earlier.then(outcome),
+                None => outcome,
+            });
+        }
+
+        match (taken, refused) {
+            (Some(taken), Some(refused)) => {
+                refused.messages.into_iter().fold(taken, |taken, message| {
+                    taken.with(message.category, message.text)
+                })
+            }
+            (Some(outcome), None) | (None, Some(outcome)) => outcome,
+            (None, None) => {
+                Outcome::failure(FailureKind::NotHere, "There is nothing here you can take.")
+            }
+        }
+    }
+
+    // Without saying who to, there must be only one person to give it to
+    pub fn do_give(&mut self, noun: &str) -> Outcome {
+        let object_idx =
+            match self.get_possession(Some(self.player), Command::Give(noun.to_string()), noun) {
+                Ok(object_idx) => object_idx,
+                Err(outcome) => return outcome,
+            };
+
+        match self.actors_here()[..] {
+            [] => Outcome::failure(FailureKind::NoActor, "There is nobody to give that to."),
+            [actor_idx] => self.move_object(Some(object_idx), Some(actor_idx)),
+            _ => Outcome::failure(
+                FailureKind::NoActor,
+                format!(
+                    "Who do you want to give {} to? Try 'give {} to' someone here.",
+                    self[object_idx].labels[0], noun
+                ),
+            ),
+        }
+    }
+
+    pub fn do_give_to(&mut self, noun: &str, actor: &str) -> Outcome {
+        let actor_idx = match self.get_actor("give that to", actor) {
+            Ok(actor_idx) => actor_idx,
+            Err(outcome) => return outcome,
+        };
+        match self.get_possession(Some(self.player), Command::Give(noun.to_string()), noun) {
+            Ok(object_idx) => self.move_object(Some(object_idx), Some(actor_idx)),
+            Err(outcome) => outcome,
+        }
+    }
+
+    // Something near enough to an actor for them to do as they are told
+    fn get_near_actor(
+        &self,
+        actor_idx: ObjectId,
+        noun: &str,
+        max_distance: Distance,
+    ) -> Result<ObjectId, Outcome> {
+        match self.get_object_index(noun, Some(actor_idx), max_distance) {
+            AmbiguousOption::Some(object_idx) => Ok(object_idx),
+            AmbiguousOption::Ambiguous => Err(Outcome::failure(
+                FailureKind::Ambiguous,
+                format!("Please be more specific about which {} you mean.", noun),
+            )),
+            AmbiguousOption::None => Err(Outcome::failure(
+                FailureKind::NotHere,
+                format!("{} can't find any {}.", self[actor_idx].labels[0], noun),
+            )),
+        }
+    }
+
+    // Only actors that obey go, get, drop or give things when told to
+    pub fn do_order(&mut self, actor: &str, order: &Command) -> Outcome {
+        let actor_idx = match self.get_actor("give orders to", actor) {
+            Ok(actor_idx) => actor_idx,
+            Err(outcome) => return outcome,
+        };
+        let name = self[actor_idx].labels[0].to_string();
+        if !self[actor_idx].obeys {
+            return Outcome::failure(FailureKind::NotAllowed, format!("{} ignores you.", name));
+        }
+
+        match order {
+            Command::Go(noun) => {
+                let passage_idx = match self.get_near_actor(actor_idx, noun, Distance::Here) {
+                    Ok(passage_idx) => passage_idx,
+                    Err(outcome) => return outcome,
+                };
+                let (Some(from), Some(to)) =
+                    (self[actor_idx].location, self[passage_idx].destination)
+                else {
+                    return Outcome::failure(
+                        FailureKind::Blocked,
+                        format!("{} can't go that way.", name),
+                    );
+                };
+                self.set_location(actor_idx, Some(to));
+                Outcome::success()
+                    .with(
+                        MessageCategory::Narration,
+                        format!(
+                            "{} goes {}.",
+                            name,
+                            self[passage_idx].labels[0].to_lowercase()
+                        ),
+                    )
+                    .with_change(Change::ObjectMoved {
+                        object: actor_idx,
+                        from: Some(from),
+                        to,
+                    })
+            }
+            Command::Get(noun) => {
+                let object_idx = match self.get_near_actor(actor_idx, noun, Distance::Here) {
+                    Ok(object_idx) => object_idx,
+                    Err(outcome) => return outcome,
+                };
+                if self[object_idx].is_fixed() || self[object_idx].kind == Kind::Actor {
+                    return Outcome::failure(
+                        FailureKind::NotAllowed,
+                        format!("{} can't take that.", name),
+                    );
+                }
+                self.order_move(actor_idx, object_idx, actor_idx, "picks up")
+            }
+            Command::Drop(noun) => {
+                let object_idx = match self.get_near_actor(actor_idx, noun, Distance::Held) {
+                    Ok(object_idx) => object_idx,
+                    Err(outcome) => return outcome,
+                };
+                match self[actor_idx].location {
+                    Some(here) => self.order_move(actor_idx, object_idx, here, "drops"),
+                    None => Outcome::failure(FailureKind::Blocked, "There is nowhere to drop it."),
                 }
             }
+            // Told to give something, actors only ever give it to the player
+            Command::Give(noun) => match self.get_near_actor(actor_idx, noun, Distance::Held) {
+                Ok(object_idx) => self.move_object(Some(object_idx), Some(self.player)),
+                Err(outcome) => outcome,
+            },
+            Command::GiveTo(noun, to) if to == "me" => {
+                self.do_order(actor, &Command::Give(noun.to_string()))
+            }
+            Command::GiveTo(_, _) => Outcome::failure(
+                FailureKind::NotAllowed,
+                format!("{} will only hand things to you.", name),
+            ),
+            Command::Unknown(input_str) if input_str.trim().is_empty() => Outcome::failure(
+                FailureKind::UnknownCommand,
+                format!("What do you want {} to do?", name),
+            ),
+            Command::Unknown(input_str) => Outcome::failure(
+                FailureKind::UnknownCommand,
+                format!("{} doesn't know how to '{}'.", name, input_str),
+            ),
+            _ => Outcome::failure(
+                FailureKind::NotAllowed,
+                format!("{} can't {} for you.", name, order),
+            ),
+        }
+    }
+
+    // Moves an object for an actor following orders, if it is not too much
+    // for what it goes to
+    fn order_move(
+        &mut self,
+        actor_idx: ObjectId,
+        object_idx: ObjectId,
+        to: ObjectId,
+        verb: &str,
+    ) -> Outcome {
+        if self[object_idx].weight + self.weight_of_contents(to) > self[to].capacity {
+            return Outcome::failure(
+                FailureKind::TooHeavy,
+                format!("That is too heavy for {}.", self[actor_idx].labels[0]),
+            );
+        }
+        let from = self[object_idx].location;
+        self.set_location(object_idx, Some(to));
+        Outcome::success()
+            .with(
+                MessageCategory::Narration,
+                format!(
+                    "{} {} {}.",
+                    self[actor_idx].labels[0], verb, self[object_idx].labels[0]
+                ),
+            )
+            .with_change(Change::ObjectMoved {
+                object: object_idx,
+                from,
+                to,
+            })
+    }
+
+    pub fn do_inventory(&self) -> Outcome {
+        let (list_string, count) = self.list_objects_at_location(self.player);
+        let outcome = if count == 0 {
+            Outcome::success().with(MessageCategory::ObjectList, "You are empty handed.")
+        } else {
+            Outcome::success().with(MessageCategory::ObjectList, list_string.trim_end())
+        };
+        outcome.without_turn()
+    }
+
+    // Hidden objects that searching revealer, or just looking at it, turns up
+    fn reveal_hidden(&mut self, revealer: ObjectId, searching: bool) -> Outcome {
+        let found: Vec<ObjectId> = self
+            .index
+            .revealed_by(revealer)
+            .iter()
+            .copied()
+            .filter(|&pos| self[pos].hidden && (searching || self[pos].reveal == Reveal::Examine))
+            .collect();
+
+        let mut outcome = Outcome::success();
+        for pos in found {
+            self.objects[pos.0].hidden = false;
+            let text = match self[pos].text_found.is_empty() {
+                true => format!("You find {}.", self.render(&self[pos].description)),
+                false => self.render(&self[pos].text_found),
+            };
+            outcome = outcome
+                .with(MessageCategory::Narration, text)
+                .with_change(Change::ObjectRevealed { object: pos });
         }
+        outcome
     }
 
-    pub fn do_give(&mut self, noun: &str) -> String {
-        let actor_loc = self.actor_here();
-        let (output, object_idx) =
-            self.get_possession(Some(LOC_PLAYER), Command::Give("give".to_string()), noun);
+    fn describe_location(&self, location: ObjectId, found: Outcome) -> Outcome {
+        let (list_string, count) = self.list_objects_at_location(location);
+        let outcome = Outcome::success()
+            .with(MessageCategory::RoomTitle, &self[location].labels[0])
+            .with(
+                MessageCategory::Description,
+                format!("You are in {}.", self.render(&self[location].description)),
+            )
+            .then(found);
 
-        output + self.move_object(object_idx, actor_loc).as_str()
+        if count == 0 {
+            outcome
+        } else {
+            outcome.with(MessageCategory::ObjectList, list_string.trim_end())
+        }
     }
 
-    pub fn do_inventory(&self) -> String {
-        let (list_string, count) = self.list_objects_at_location(LOC_PLAYER);
+    fn describe_object(&self, obj_idx: ObjectId, location: ObjectId, found: Outcome) -> Outcome {
+        let (list_string, count) = self.list_objects_at_location(location);
+        let outcome = Outcome::success()
+            .with(
+                MessageCategory::Description,
+                self.render(&self[obj_idx].details),
+            )
+            .then(found);
+
         if count == 0 {
-            "You are empty handed.\n".to_string()
+            outcome
         } else {
-            list_string
+            outcome.with(MessageCategory::ObjectList, list_string.trim_end())
         }
     }
 
-    pub fn do_look(&self, noun: &str) -> String {
+    pub fn do_look(&mut self, noun: &str) -> Outcome {
         match noun {
             "around" | "" => {
-                let (list_string, _) =
-                    self.list_objects_at_location(self.objects[LOC_PLAYER].location.unwrap());
-                format!(
-                    "{}\nYou are in {}.\n",
-                    self.objects[self.objects[LOC_PLAYER].location.unwrap()].labels[0],
-                    self.objects[self.objects[LOC_PLAYER].location.unwrap()].description
-                ) + list_string.as_str()
+                let location = self[self.player].location.unwrap();
+                let found = self.reveal_hidden(location, false);
+                self.describe_location(location, found)
             }
             _ => {
-                let (output_vis, obj_opt) = self.get_visible("what you want to look at", noun);
-                let player_to_obj = self.get_distance(Some(LOC_PLAYER), obj_opt);
-
-                match (player_to_obj, obj_opt) {
-                    (Distance::HereContained, _) => {
-                        output_vis + "Hard to see, you should try to get it first.\n"
-                    }
-                    (Distance::OverThere, _) => output_vis + "Too far away, move closer please.\n",
-                    (Distance::NotHere, _) => {
-                        output_vis + &format!("You don't see any {} here.\n", noun)
-                    }
-                    (Distance::UnknownObject, _) => output_vis,
-                    (Distance::Location, Some(obj_idx)) => {
-                        let (list_string, _) = self
-                            .list_objects_at_location(self.objects[LOC_PLAYER].location.unwrap());
-                        output_vis
-                            + &format!("{}\n{}\n", self.objects[obj_idx].details, list_string)
+                let obj_idx = match self.get_visible("what you want to look at", noun) {
+                    Ok(obj_idx) => obj_idx,
+                    Err(outcome) => return outcome,
+                };
+
+                match self.get_distance(Some(self.player), Some(obj_idx)) {
+                    Distance::HereContained => Outcome::failure(
+                        FailureKind::TooFar,
+                        "Hard to see, you should try to get it first.",
+                    ),
+                    Distance::OverThere => {
+                        Outcome::failure(FailureKind::TooFar, "Too far away, move closer please.")
                     }
-                    (_, Some(obj_idx)) => {
-                        let (list_string, _) =
-                            self.list_objects_at_location(self.objects[obj_idx].location.unwrap());
-                        output_vis
-                            + &format!("{}\n{}\n", self.objects[obj_idx].details, list_string)
+                    Distance::NotHere => Outcome::failure(
+                        FailureKind::NotHere,
+                        format!("You don't see any {} here.", noun),
+                    ),
+                    Distance::Location => {
+                        let found = self.reveal_hidden(obj_idx, false);
+                        self.describe_object(obj_idx, self[self.player].location.unwrap(), found)
                     }
-                    (_, None) => {
-                        // Should never be here
-                        output_vis + "How can you look at nothing?.\n"
+                    _ => {
+                        let found = self.reveal_hidden(obj_idx, false);
+                        self.describe_object(obj_idx, self[obj_idx].location.unwrap(), found)
                     }
                 }
             }
         }
     }
 
-    fn move_player(&mut self, obj_opt: Option<usize>) -> String {
-        let go_string = format!("{}\n", self.objects[obj_opt.unwrap()].text_go);
-        let obj_dst = obj_opt.and_then(|a| self.objects[a].destination);
-        if obj_dst != None {
-            self.objects[LOC_PLAYER].location = obj_dst;
-            go_string + "\n" + &self.do_look("around")
-        } else {
-            go_string
+    // Searching with no object searches wherever the player is
+    pub fn do_search(&mut self, noun: &str) -> Outcome {
+        let obj_idx = match noun {
+            "" => self[self.player].location.unwrap(),
+            _ => match self.get_visible("what you want to search", noun) {
+                Ok(obj_idx) => obj_idx,
+                Err(outcome) => return outcome,
+            },
+        };
+
+        match self.get_distance(Some(self.player), Some(obj_idx)) {
+            Distance::OverThere => {
+                Outcome::failure(FailureKind::TooFar, "Too far away, move closer please.")
+            }
+            _ => {
+                let found = self.reveal_hidden(obj_idx, true);
+                if found.messages.is_empty() {
+                    Outcome::success().with(
+                        MessageCategory::Narration,
+                        format!("You search {} but find nothing.", self[obj_idx].labels[0]),
+                    )
+                } else {
+                    found
+                }
+            }
         }
     }
 
-    pub fn do_go(&mut self, noun: &str) -> String {
-        let (output_vis, obj_opt) = self.get_visible("where you want to go", noun);
-
-        match self.get_distance(Some(LOC_PLAYER), obj_opt) {
-            Distance::OverThere => self.move_player(obj_opt),
-            Distance::NotHere => {
-                format!("You don't see any {} here.\n", noun)
+    fn move_player(&mut self, obj_idx: ObjectId) -> Outcome {
+        let go_string = self.render(&self[obj_idx].text_go);
+        let player_loc = self[self.player].location;
+
+        match self[obj_idx].destination {
+            Some(obj_dst) => {
+                self.set_location(self.player, Some(obj_dst));
+                Outcome::success()
+                    .with(MessageCategory::Narration, go_string)
+                    .with_change(Change::PlayerMoved {
+                        from: player_loc,
+                        to: obj_dst,
+                    })
+                    .then(self.do_look("around"))
             }
-            Distance::UnknownObject => output_vis,
-            _ => self.move_player(obj_opt),
+            None => Outcome::failure(FailureKind::Blocked, go_string),
+        }
+    }
+
+    pub fn do_go(&mut self, noun: &str) -> Outcome {
+        match self.get_visible("where you want to go", noun) {
+            Ok(obj_idx) => self.move_player(obj_idx),
+            Err(outcome) => outcome,
         }
     }
 }
 
+impl Index<ObjectId> for World {
+    type Output = Object;
+
+    fn index(&self, id: ObjectId) -> &Object {
+        &self.objects[id.0]
+    }
+}
+
 impl Object {
+    pub fn is_fixed(&self) -> bool {
+        self.fixed || self.kind.is_fixed()
+    }
+
+    #[allow(clippy::too_many_arguments)]
     fn new(
+        new_id: String,
+        new_kind: Kind,
         new_labels: Vec<String>,
         new_description: String,
-        new_location: Option<usize>,
-        new_destination: Option<usize>,
-        new_prospect: Option<usize>,
+        new_location: Option<ObjectId>,
+        new_destination: Option<ObjectId>,
+        new_prospect: Option<ObjectId>,
         new_details: String,
         new_contents: String,
         new_text_go: String,
+        new_fixed: bool,
+        new_refusal: String,
         new_weight: isize,
         new_capacity: isize,
         new_health: isize,
+        new_properties: BTreeMap<String, String>,
+        new_hidden: bool,
+        new_revealed_by: Option<ObjectId>,
+        new_reveal: Reveal,
+        new_text_found: String,
+        new_reverse_of: Option<ObjectId>,
+        new_topics: Vec<Topic>,
+        new_dialogue: Vec<Node>,
+        new_behaviours: Vec<Behaviour>,
+        new_obeys: bool,
     ) -> Object {
         Object {
+            id: new_id,
+            kind: new_kind,
             labels: new_labels,
             description: new_description,
             location: new_location,
@@ -693,17 +1844,160 @@ impl Object {
             details: new_details,
             contents: new_contents,
             text_go: new_text_go,
+            fixed: new_fixed,
+            refusal: new_refusal,
             weight: new_weight,
             capacity: new_capacity,
             health: new_health,
+            properties: new_properties,
+            hidden: new_hidden,
+            revealed_by: new_revealed_by,
+            reveal: new_reveal,
+            text_found: new_text_found,
+            reverse_of: new_reverse_of,
+            topics: new_topics,
+            dialogue: new_dialogue,
+            behaviours: new_behaviours,
+            obeys: new_obeys,
         }
     }
 }
 
+impl SavedObject {
+    pub fn reverse_id(&self) -> Option<String> {
+        let reverse = self.reverse.as_ref()?;
+        Some(match reverse.id.is_empty() {
+            true => format!("{}_reverse", self.id),
+            false => reverse.id.to_string(),
+        })
+    }
+
+    // The passage back from a two way passage. origin is the location
+    // the passage leads back to, when there is one.
+    fn reversed(&self, origin: Option<&SavedObject>) -> Option<SavedObject> {
+        let reverse = self.reverse.as_ref()?;
+
+        let labels: Vec<String> = match reverse.labels.is_empty() {
+            true => self
+                .labels
+                .iter()
+                .map(|label| opposite_direction(label))
+                .collect(),
+            false => reverse.labels.clone(),
+        };
+        let direction = labels.first().cloned().unwrap_or_default().to_lowercase();
+        let origin_description =
+            origin.map_or(self.location.as_str(), |origin| origin.description.as_str());
+
+        Some(SavedObject {
+            id: self.reverse_id()?,
+            kind: Kind::Passage,
+            description: match reverse.description.is_empty() {
+                true => format!("a passage {} to {}", direction, origin_description),
+                false => reverse.description.to_string(),
+            },
+            labels,
+            location: self.destination.to_string(),
+            destination: self.location.to_string(),
+            prospect: DEF_PROSPECT.to_string(),
+            details: reverse.details.to_string(),
+            contents: DEF_CONTENTS.to_string(),
+            text_go: match reverse.text_go.is_empty() {
+                true => format!("You go {} to {}.", direction, origin_description),
+                false => reverse.text_go.to_string(),
+            },
+            fixed: false,
+            refusal: DEF_REFUSAL.to_string(),
+            weight: DEF_WEIGHT,
+            capacity: DEF_CAPACITY,
+            health: DEF_HEALTH,
+            properties: BTreeMap::new(),
+            reverse: None,
+            hidden: false,
+            revealed_by: String::new(),
+            reveal: Reveal::default(),
+            text_found: String::new(),
+            topics: Vec::new(),
+            dialogue: Vec::new(),
+            behaviours: Vec::new(),
+            obeys: false,
+        })
+    }
+
+    // Fields that do not fit the object's kind, described for the author
+    pub fn kind_problems(&self) -> Vec<String> {
+        let mut problems: Vec<String> = Vec::new();
+
+        match (self.kind, self.location.is_empty()) {
+            (Kind::Location, false) => problems.push(format!(
+                "is a location, so it cannot be inside '{}'",
+                self.location
+            )),
+            (Kind::Location, true) | (_, false) => (),
+            (kind, true) => problems.push(format!(
+                "is {}, but it is not anywhere",
+                kind.with_article()
+            )),
+        }
+        if self.kind == Kind::Passage {
+            if self.destination.is_empty() {
+                problems.push("is a passage, but it has no destination".into());
+            }
+        } else if !self.destination.is_empty() || !self.prospect.is_empty() {
+            problems.push(format!(
+                "is {}, so only a passage can have a destination or prospect",
+                self.kind.with_article()
+            ));
+        }
+        if self.kind == Kind::Location && self.hidden {
+            problems.push("is a location, so it cannot be hidden".into());
+        }
+        if self.kind != Kind::Passage && self.reverse.is_some() {
+            problems.push(format!(
+                "is {}, so only a passage can have a reverse",
+                self.kind.with_article()
+            ));
+        }
+        if self.kind != Kind::Actor && !(self.topics.is_empty() && self.dialogue.is_empty()) {
+            problems.push(format!(
+                "is {}, so only an actor can have topics or dialogue",
+                self.kind.with_article()
+            ));
+        }
+        if self.kind != Kind::Actor && !self.behaviours.is_empty() {
+            problems.push(format!(
+                "is {}, so only an actor can have behaviours",
+                self.kind.with_article()
+            ));
+        }
+        if self.kind != Kind::Actor && self.obeys {
+            problems.push(format!(
+                "is {}, so only an actor can obey",
+                self.kind.with_article()
+            ));
+        }
+        if !self.fixed && !self.kind.is_fixed() && !is_default_refusal(&self.refusal) {
+            problems.push(format!(
+                "is {} that can be picked up, so it cannot have a refusal",
+                self.kind.with_article()
+            ));
+        }
+        problems
+    }
+}
+
 impl SavedWorld {
-    fn new(new_objects: Vec<SavedObject>) -> SavedWorld {
+    fn new(
+        new_player: String,
+        new_objects: Vec<SavedObject>,
+        new_goals: Vec<SavedGoal>,
+        new_turn: u32,
+    ) -> SavedWorld {
         SavedWorld {
+            player: new_player,
             objects: new_objects,
+            goals: new_goals,
+            turn: new_turn,
         }
     }
 }
@@ -712,114 +2006,283 @@ impl From<&World> for SavedWorld {
     fn from(value: &World) -> Self {
         let mut new_vec_of_objects: Vec<SavedObject> = Vec::new();
 
-        for item in &value.objects {
+        let id_of = |object: Option<ObjectId>| match object {
+            Some(object) => value[object].id.to_string(),
+            None => "".to_string(),
+        };
+
+        // Passages back are written as part of the passage they reverse
+        let reverses: HashMap<ObjectId, &Object> = value
+            .iter()
+            .filter_map(|(_, item)| item.reverse_of.map(|forward| (forward, item)))
+            .collect();
+
+        for (pos, item) in value.iter() {
+            if item.reverse_of.is_some() {
+                continue;
+            }
             new_vec_of_objects.push(SavedObject {
+                id: item.id.to_string(),
+                kind: item.kind,
                 labels: item.labels.clone(),
                 description: item.description.to_string(),
-                location: match item.location {
-                    Some(location) => value.objects[location].labels[0].to_string(),
-                    None => "".to_string(),
-                },
-                destination: match item.destination {
-                    Some(destination) => value.objects[destination].labels[0].to_string(),
-                    None => "".to_string(),
-                },
-                prospect: match item.prospect {
-                    Some(prospect) => value.objects[prospect].labels[0].to_string(),
-                    None => "".to_string(),
+                location: id_of(item.location),
+                destination: id_of(item.destination),
+                // A prospect that is the destination is left out, as it
+                // would have been in the game file
+                prospect: if item.prospect == item.destination {
+                    DEF_PROSPECT.to_string()
+                } else {
+                    id_of(item.prospect)
                 },
                 details: item.details.to_string(),
                 contents: item.contents.to_string(),
                 text_go: item.text_go.to_string(),
+                fixed: item.fixed,
+                refusal: item.refusal.to_string(),
                 weight: item.weight,
                 capacity: item.capacity,
                 health: item.health,
+                properties: item.properties.clone(),
+                reverse: reverses.get(&pos).map(|back| SavedReverse {
+                    id: back.id.to_string(),
+                    labels: back.labels.clone(),
+                    description: back.description.to_string(),
+                    details: back.details.to_string(),
+                    text_go: back.text_go.to_string(),
+                }),
+                hidden: item.hidden,
+                revealed_by: id_of(item.revealed_by),
+                reveal: item.reveal,
+                text_found: item.text_found.to_string(),
+                topics: dialogue::saved_topics(&item.topics, &|object| id_of(Some(object))),
+                dialogue: dialogue::saved_dialogue(&item.dialogue, &|object| id_of(Some(object))),
+                behaviours: behaviour::saved_behaviours(&item.behaviours, &|object| {
+                    id_of(Some(object))
+                }),
+                obeys: item.obeys,
             });
         }
 
         SavedWorld {
+            player: value[value.player].id.to_string(),
             objects: new_vec_of_objects,
+            goals: value
+                .goals
+                .iter()
+                .map(|goal| SavedGoal {
+                    object: value[goal.object].id.to_string(),
+                    location: value[goal.location].id.to_string(),
+                })
+                .collect(),
+            turn: value.turn,
         }
     }
 }
 
+impl SavedWorld {
+    pub fn read_from_file(game_file: &str) -> Result<SavedWorld, LoadError> {
+        let game_file_data = read_to_string(Path::new(game_file))?;
+        Ok(ron::from_str(&game_file_data)?)
+    }
+
+    // An object missing its id cannot be referred to, even by an empty name
+    fn find_object(&self, id: &str) -> Option<ObjectId> {
+        self.objects
+            .iter()
+            .position(|item| !id.is_empty() && item.id == id)
+            .map(ObjectId)
+    }
+
+    // Where each id is first used, so names resolve without a scan apiece
+    fn id_map(objects: &[SavedObject]) -> HashMap<&str, ObjectId> {
+        let mut ids: HashMap<&str, ObjectId> = HashMap::new();
+        for (pos, item) in objects.iter().enumerate() {
+            ids.entry(item.id.as_str()).or_insert(ObjectId(pos));
+        }
+        ids
+    }
+
+    // Every object, with the passage back from each two way passage right
+    // after it, and for each the passage it is the reverse of
+    fn with_reverses(&self) -> (Vec<SavedObject>, Vec<Option<ObjectId>>) {
+        let ids = SavedWorld::id_map(&self.objects);
+        let mut objects: Vec<SavedObject> = Vec::new();
+        let mut reverse_of: Vec<Option<ObjectId>> = Vec::new();
+
+        for item in &self.objects {
+            let forward = ObjectId(objects.len());
+            objects.push(item.clone());
+            reverse_of.push(None);
+
+            let origin = ids
+                .get(item.location.as_str())
+                .map(|origin| &self.objects[origin.0]);
+            if let Some(reversed) = item.reversed(origin) {
+                objects.push(reversed);
+                reverse_of.push(Some(forward));
+            }
+        }
+        (objects, reverse_of)
+    }
+
+    // An empty name leaves the field unset
+    fn resolve_name(
+        ids: &HashMap<&str, ObjectId>,
+        item: &SavedObject,
+        field: &str,
+        name: &str,
+        errors: &mut Vec<ParseError>,
+    ) -> Option<ObjectId> {
+        if name.is_empty() {
+            return None;
+        }
+        SavedWorld::resolve_required_name(ids, item, field, name, errors)
+    }
+
+    // For names that have to be given, such as in effects and actions
+    fn resolve_required_name(
+        ids: &HashMap<&str, ObjectId>,
+        item: &SavedObject,
+        field: &str,
+        name: &str,
+        errors: &mut Vec<ParseError>,
+    ) -> Option<ObjectId> {
+        let result = ids.get(name).copied();
+        if result.is_none() {
+            errors.push(ParseError::UnknownName {
+                object: item.id.to_string(),
+                field: field.into(),
+                name: name.into(),
+            });
+        }
+        result
+    }
+}
+
 impl TryInto<World> for SavedWorld {
-    type Error = ParseError;
+    type Error = LoadError;
 
     fn try_into(self) -> Result<World, Self::Error> {
         let mut new_vec_of_objects: Vec<Object> = Vec::new();
+        let mut errors: Vec<ParseError> = Vec::new();
+        let (objects, reverse_of) = self.with_reverses();
+        let ids = SavedWorld::id_map(&objects);
+
+        // Resolve every name before giving up, so that all of the
+        // problems in a game file are reported together
+        for (pos, item) in objects.iter().enumerate() {
+            if item.id.is_empty() {
+                errors.push(ParseError::EmptyId {
+                    object: item.labels.first().cloned().unwrap_or_default(),
+                });
+            } else if ids.get(item.id.as_str()) != Some(&ObjectId(pos)) {
+                errors.push(ParseError::DuplicateId {
+                    id: item.id.to_string(),
+                });
+            }
+            for problem in item.kind_problems() {
+                errors.push(ParseError::WrongKind {
+                    object: item.id.to_string(),
+                    problem,
+                });
+            }
 
-        'items: for item in &self.objects {
-            let mut new_object = Object::new(
+            let location =
+                SavedWorld::resolve_name(&ids, item, "location", &item.location, &mut errors);
+            let destination =
+                SavedWorld::resolve_name(&ids, item, "destination", &item.destination, &mut errors);
+            let prospect = if item.prospect.is_empty() {
+                // If no prospect is given then use the destination
+                destination
+            } else {
+                SavedWorld::resolve_name(&ids, item, "prospect", &item.prospect, &mut errors)
+            };
+            let revealed_by =
+                SavedWorld::resolve_name(&ids, item, "revealed_by", &item.revealed_by, &mut errors);
+
+            new_vec_of_objects.push(Object::new(
+                item.id.to_string(),
+                item.kind,
                 item.labels.clone(),
                 item.description.to_string(),
-                None,
-                None,
-                None,
+                location,
+                destination,
+                prospect,
                 item.details.to_string(),
                 item.contents.to_string(),
                 item.text_go.to_string(),
+                item.fixed,
+                item.refusal.to_string(),
                 item.weight,
                 item.capacity,
                 item.health,
-            );
+                item.properties.clone(),
+                item.hidden,
+                revealed_by,
+                item.reveal,
+                item.text_found.to_string(),
+                reverse_of[pos],
+                dialogue::resolve_topics(&ids, item, &mut errors),
+                dialogue::resolve_dialogue(&ids, item, &mut errors),
+                behaviour::resolve_behaviours(&ids, item, &mut errors),
+                item.obeys,
+            ));
+        }
 
-            let mut found_location: bool = item.location.is_empty();
-            let mut found_destination: bool = item.destination.is_empty();
-            let mut found_prospect: bool = item.prospect.is_empty();
+        let player = ids.get(self.player.as_str()).copied();
+        if player.is_none() {
+            errors.push(ParseError::UnknownName {
+                object: "World".into(),
+                field: "player".into(),
+                name: self.player.to_string(),
+            });
+        }
+        if let Some(player) = player.filter(|player| objects[player.0].kind != Kind::Actor) {
+            errors.push(ParseError::WrongKind {
+                object: self.player.to_string(),
+                problem: format!(
+                    "is the player, so it must be an actor, not {}",
+                    objects[player.0].kind.with_article()
+                ),
+            });
+        }
 
-            for (pos, internal_item) in self.objects.iter().enumerate() {
-                if item.location == internal_item.labels[0] {
-                    new_object.location = Some(pos);
-                    found_location = true;
+        let mut new_vec_of_goals: Vec<Goal> = Vec::new();
+
+        for goal in &self.goals {
+            let object = ids.get(goal.object.as_str()).copied();
+            let location = ids.get(goal.location.as_str()).copied();
+
+            for (field, name, found) in [
+                ("goal object", &goal.object, object),
+                ("goal location", &goal.location, location),
+            ] {
+                if found.is_none() {
+                    errors.push(ParseError::UnknownName {
+                        object: "World".into(),
+                        field: field.into(),
+                        name: name.to_string(),
+                    });
                 }
-                if item.destination == internal_item.labels[0] {
-                    new_object.destination = Some(pos);
-                    found_destination = true;
-
-                    // If no prospect is given then use the destination
-                    if item.prospect.len() == 0 {
-                        new_object.prospect = Some(pos);
-                        found_prospect = true;
-                    }
-                }
-                if item.prospect == internal_item.labels[0] {
-                    new_object.prospect = Some(pos);
-                    found_prospect = true;
-                }
-                if found_location && found_destination && found_prospect {
-                    new_vec_of_objects.push(new_object);
-                    continue 'items;
-                }
-            }
-
-            if !found_location {
-                return Err(ParseError::UnknownName(format!(
-                    "Unknown location '{}'",
-                    item.location
-                )));
-            }
-
-            if !found_destination {
-                return Err(ParseError::UnknownName(format!(
-                    "Unknown destination '{}'",
-                    item.destination
-                )));
             }
 
-            if !found_prospect {
-                return Err(ParseError::UnknownName(format!(
-                    "Unknown prospect '{}'",
-                    item.prospect
-                )));
+            if let (Some(object), Some(location)) = (object, location) {
+                new_vec_of_goals.push(Goal { object, location });
             }
-
-            new_vec_of_objects.push(new_object);
-            return Err(ParseError::UnknownName("How are we here?".into()));
         }
 
+        let Some(player) = player.filter(|_| errors.is_empty()) else {
+            return Err(LoadError::Semantic(errors));
+        };
+
         let result_world = World {
+            index: WorldIndex::new(&new_vec_of_objects),
             objects: new_vec_of_objects,
+            player,
+            goals: new_vec_of_goals,
+            turn: self.turn,
+            conversation: None,
         };
 
         Ok(result_world)
@@ -833,9 +2296,24 @@ impl Serialize for World {
     {
         let serializeable_struct: SavedWorld = SavedWorld::from(self);
 
-        // 1 is the number of fields in the struct.
-        let mut state = serializer.serialize_struct("World", 1)?;
+        // 4 is the number of fields in the struct.
+        let mut state = serializer.serialize_struct("World", 4)?;
+        if is_default_player(&serializeable_struct.player) {
+            state.skip_field("player")?;
+        } else {
+            state.serialize_field("player", &serializeable_struct.player)?;
+        }
         state.serialize_field("objects", &serializeable_struct.objects)?;
+        if serializeable_struct.goals.is_empty() {
+            state.skip_field("goals")?;
+        } else {
+            state.serialize_field("goals", &serializeable_struct.goals)?;
+        }
+        if is_first_turn(&serializeable_struct.turn) {
+            state.skip_field("turn")?;
+        } else {
+            state.serialize_field("turn", &serializeable_struct.turn)?;
+        }
         state.end()
     }
 }
@@ -846,7 +2324,10 @@ impl<'de> Deserialize<'de> for World {
         D: Deserializer<'de>,
     {
         enum Field {
+            Player,
             Objects,
+            Goals,
+            Turn,
         }
 
         impl<'de> Deserialize<'de> for Field {
@@ -860,7 +2341,7 @@ impl<'de> Deserialize<'de> for World {
                     type Value = Field;
 
                     fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-                        formatter.write_str("`objects`")
+                        formatter.write_str("`player`, `objects`, `goals` or `turn`")
                     }
 
                     fn visit_str<E>(self, value: &str) -> Result<Field, E>
@@ -868,7 +2349,10 @@ impl<'de> Deserialize<'de> for World {
                         E: de::Error,
                     {
                         match value {
+                            "player" => Ok(Field::Player),
                             "objects" => Ok(Field::Objects),
+                            "goals" => Ok(Field::Goals),
+                            "turn" => Ok(Field::Turn),
                             _ => Err(de::Error::unknown_field(value, FIELDS)),
                         }
                     }
@@ -891,32 +2375,61 @@ impl<'de> Deserialize<'de> for World {
             where
                 V: SeqAccess<'de>,
             {
+                let player = seq.next_element()?.unwrap_or_else(default_player);
                 let objects = seq
                     .next_element()?
-                    .ok_or_else(|| de::Error::invalid_length(1, &self))?;
-                Ok(SavedWorld::new(objects))
+                    .ok_or_else(|| de::Error::invalid_length(2, &self))?;
+                let goals = seq.next_element()?.unwrap_or_default();
+                let turn = seq.next_element()?.unwrap_or_default();
+                Ok(SavedWorld::new(player, objects, goals, turn))
             }
             fn visit_map<V>(self, mut map: V) -> Result<SavedWorld, V::Error>
             where
                 V: MapAccess<'de>,
             {
+                let mut player = None;
                 let mut objects = None;
+                let mut goals = None;
+                let mut turn = None;
                 while let Some(key) = map.next_key()? {
                     match key {
+                        Field::Player => {
+                            if player.is_some() {
+                                return Err(de::Error::duplicate_field("player"));
+                            }
+                            player = Some(map.next_value()?);
+                        }
                         Field::Objects => {
                             if objects.is_some() {
                                 return Err(de::Error::duplicate_field("objects"));
                             }
                             objects = Some(map.next_value()?);
                         }
+                        Field::Goals => {
+                            if goals.is_some() {
+                                return Err(de::Error::duplicate_field("goals"));
+                            }
+                            goals = Some(map.next_value()?);
+                        }
+                        Field::Turn => {
+                            if turn.is_some() {
+                                return Err(de::Error::duplicate_field("turn"));
+                            }
+                            turn = Some(map.next_value()?);
+                        }
                     }
                 }
                 let objects = objects.ok_or_else(|| de::Error::missing_field("objects"))?;
-                Ok(SavedWorld::new(objects))
+                Ok(SavedWorld::new(
+                    player.unwrap_or_else(default_player),
+                    objects,
+                    goals.unwrap_or_default(),
+                    turn.unwrap_or_default(),
+                ))
             }
         }
 
-        const FIELDS: &[&str] = &["objects"];
+        const FIELDS: &[&str] = &["player", "objects", "goals", "turn"];
         let internal_extract = deserializer.deserialize_struct("World", FIELDS, SavedWorldVisitor);
         match internal_extract {
             Ok(extracted_val) => {
@@ -934,7 +2447,35 @@ impl<'de> Deserialize<'de> for World {
     }
 }
 
+// Every verb the player can use, including 'script', which the Session
+// handles itself
+pub const VERBS: &[&str] = &[
+    "ask",
+    "drop",
+    "examine",
+    "get",
+    "give",
+    "go",
+    "inventory",
+    "look",
+    "quit",
+    "script",
+    "search",
+    "talk",
+    "tell",
+];
+
 pub fn parse(input_str: String) -> Command {
+    // "robot, go aft" tells someone else what to do
+    match input_str.split_once(',') {
+        Some((actor, order)) => {
+            Command::Order(actor.trim().to_lowercase(), order.trim().to_string())
+        }
+        None => parse_command(input_str),
+    }
+}
+
+fn parse_command(input_str: String) -> Command {
     let lc_input_str = input_str.to_lowercase();
     let mut split_input_iter = lc_input_str.split_whitespace();
 
@@ -947,35 +2488,652 @@ pub fn parse(input_str: String) -> Command {
         }
     });
 
+    // A bare number picks a reply in a conversation
+    if let (Ok(choice), true) = (verb.parse::<usize>(), noun.is_empty()) {
+        return Command::Choose(choice);
+    }
+
     match verb.as_str() {
-        "ask" => Command::Ask(noun),
+        "ask" => match (noun.split_once(" about "), noun.split_once(" for ")) {
+            (Some((actor, topic)), _) => Command::AskAbout(actor.to_string(), topic.to_string()),
+            (None, Some((actor, object))) => Command::AskFor(actor.to_string(), object.to_string()),
+            (None, None) => Command::AskAbout(noun, String::new()),
+        },
         "drop" => Command::Drop(noun),
         "get" => Command::Get(noun),
-        "give" => Command::Give(noun),
+        "give" => match noun.split_once(" to ") {
+            Some((object, actor)) => Command::GiveTo(object.to_string(), actor.to_string()),
+            None => Command::Give(noun),
+        },
         "go" => Command::Go(noun),
         "inventory" => Command::Inventory,
-        "look" => Command::Look(noun),
+        "examine" => Command::Look(noun),
+        "look" => match noun.split_once(' ') {
+            Some(("under" | "behind" | "in" | "inside", object)) => {
+                Command::Search(object.to_string())
+            }
+            _ => Command::Look(noun),
+        },
         "quit" => Command::Quit,
+        "search" => Command::Search(noun),
+        "talk" => match noun.split_once(' ') {
+            Some(("to", actor)) => Command::Talk(actor.to_string()),
+            _ if noun == "to" => Command::Talk(String::new()),
+            _ => Command::Talk(noun),
+        },
+        "tell" => match noun.split_once(" about ") {
+            Some((actor, topic)) => Command::Tell(actor.to_string(), topic.to_string()),
+            None => Command::Tell(noun, String::new()),
+        },
         _ => Command::Unknown(input_str.trim().to_string()),
     }
 }
 
-pub fn get_input() -> Command {
+pub fn read_input(input: &mut impl InputSource, output: &mut impl OutputSink) -> Option<String> {
     // Prompt
-    print!("> ");
-    io::stdout().flush().unwrap();
-
-    let mut input_str = String::new();
+    if !input.shows_prompt() {
+        output.write("> ").ok()?;
+    }
+    output.flush().ok()?;
+
+    let Some(input_str) = input.read_line() else {
+        // Finish the prompt line before the game ends
+        output.write("\n").ok()?;
+        return None;
+    };
+
+    // The terminal has already shown what was typed
+    if !input.is_interactive() {
+        output.write(&input_str).ok()?;
+        output.write("\n").ok()?;
+    }
+    output.write("\n").ok()?;
 
-    io::stdin()
-        .read_line(&mut input_str)
-        .expect("Failed to read move");
-    println!();
+    Some(input_str)
+}
 
+pub fn get_input(input: &mut impl InputSource, output: &mut impl OutputSink) -> Option<Command> {
     // Parse & Return
-    parse(input_str)
+    read_input(input, output).map(parse)
+}
+
+pub fn update_screen(sink: &mut impl OutputSink, output: String) -> io::Result<()> {
+    sink.write(&output)?;
+    sink.write("\n")?;
+    sink.flush()
 }
 
-pub fn update_screen(output: String) {
-    println!("{}", output);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use outcome::OutcomeKind;
+
+    fn object(text: &str) -> SavedObject {
+        ron::from_str(text).unwrap()
+    }
+
+    fn load(objects: &str) -> Result<World, LoadError> {
+        let saved: SavedWorld = ron::from_str(&format!("(objects: [{}])", objects)).unwrap();
+        saved.try_into()
+    }
+
+    #[test]
+    fn objects_that_fit_their_kind_have_no_problems() {
+        for text in [
+            r#"(id: "bridge", kind: Location, labels: ["Bridge"], description: "the bridge")"#,
+            r#"(id: "pen", kind: Item, labels: ["Pen"], description: "a pen", location: "bridge")"#,
+            r#"(id: "aft", kind: Passage, labels: ["Aft"], description: "a passage",
+                location: "bridge", destination: "galley", prospect: "galley")"#,
+            r#"(id: "panel", kind: Scenery, labels: ["Panel"], description: "a panel",
+                location: "bridge", refusal: "It is bolted down.")"#,
+        ] {
+            assert_eq!(
+                object(text).kind_problems(),
+                Vec::<String>::new(),
+                "{}",
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn fields_that_do_not_fit_the_kind_are_problems() {
+        for (text, problem) in [
+            (
+                r#"(id: "bridge", kind: Location, labels: ["Bridge"], description: "the bridge",
+                    location: "ship")"#,
+                "is a location, so it cannot be inside 'ship'",
+            ),
+            (
+                r#"(id: "pen", kind: Item, labels: ["Pen"], description: "a pen")"#,
+                "is an item, but it is not anywhere",
+            ),
+            (
+                r#"(id: "aft", kind: Passage, labels: ["Aft"], description: "a passage",
+                    location: "bridge")"#,
+                "is a passage, but it has no destination",
+            ),
+            (
+                r#"(id: "pen", kind: Item, labels: ["Pen"], description: "a pen",
+                    location: "bridge", destination: "galley")"#,
+                "is an item, so only a passage can have a destination or prospect",
+            ),
+            (
+                r#"(id: "pen", kind: Item, labels: ["Pen"], description: "a pen",
+                    location: "bridge", refusal: "No.")"#,
+                "is an item that can be picked up, so it cannot have a refusal",
+            ),
+        ] {
+            assert_eq!(object(text).kind_problems(), vec![problem.to_string()]);
+        }
+    }
+
+    #[test]
+    fn wrong_kinds_stop_a_world_loading() {
+        let load_res = load(
+            r#"(id: "player", kind: Actor, labels: ["Yourself"], description: "you",
+                location: "bridge"),
+               (id: "bridge", kind: Location, labels: ["Bridge"], description: "the bridge"),
+               (id: "pen", kind: Item, labels: ["Pen"], description: "a pen")"#,
+        );
+        let Err(LoadError::Semantic(parse_errs)) = load_res else {
+            panic!("a pen that is nowhere should not load");
+        };
+        assert_eq!(parse_errs.len(), 1);
+        assert!(matches!(
+            &parse_errs[0],
+            ParseError::WrongKind { object, .. } if object == "pen"
+        ));
+    }
+
+    // A storeroom with things to take, one too heavy to carry and three
+    // that are fixed in place
+    fn storeroom() -> World {
+        load(
+            r#"(id: "player", kind: Actor, labels: ["Yourself"], description: "you",
+                location: "store", capacity: 10),
+               (id: "store", kind: Location, labels: ["Store"], description: "a storeroom"),
+               (id: "pen", kind: Item, labels: ["Pen"], description: "a pen", location: "store",
+                weight: 1),
+               (id: "photo", kind: Item, labels: ["Photo"], description: "a photo",
+                location: "store", weight: 1),
+               (id: "anvil", kind: Item, labels: ["Anvil"], description: "an anvil",
+                location: "store", weight: 50),
+               (id: "panel", kind: Scenery, labels: ["Panel"], description: "a panel",
+                location: "store", refusal: "It is bolted down."),
+               (id: "crate", kind: Item, labels: ["Crate"], description: "a crate",
+                location: "store", weight: 1, fixed: true, refusal: "It will not budge."),
+               (id: "shelf", kind: Item, labels: ["Shelf"], description: "a shelf",
+                location: "store", weight: 1, fixed: true)"#,
+        )
+        .unwrap()
+    }
+
+    fn held(world: &World, id: &str) -> bool {
+        world[world.find_id(id).unwrap()].location == Some(world.player)
+    }
+
+    #[test]
+    fn fixed_things_give_their_refusal() {
+        let mut world = storeroom();
+        for (input_str, refusal) in [
+            ("get panel", "It is bolted down."),
+            ("get crate", "It will not budge."),
+            ("get shelf", DEF_REFUSAL),
+        ] {
+            let outcome = world.update_state(&parse(input_str.to_string()));
+            assert_eq!(
+                outcome.kind,
+                OutcomeKind::Failure(FailureKind::NotAllowed),
+                "{}",
+                input_str
+            );
+            assert_eq!(outcome.to_string(), format!("{}\n", refusal));
+        }
+        assert!(!held(&world, "panel") && !held(&world, "crate") && !held(&world, "shelf"));
+    }
+
+    #[test]
+    fn get_all_takes_what_it_can() {
+        let mut world = storeroom();
+        let outcome = world.update_state(&parse("get all".to_string()));
+        assert!(outcome.is_success());
+        assert!(held(&world, "pen") && held(&world, "photo"));
+        assert!(!held(&world, "anvil"));
+        assert!(!held(&world, "panel") && !held(&world, "crate") && !held(&world, "shelf"));
+
+        // Only the item that could not be carried is mentioned
+        let text = outcome.to_string();
+        assert!(text.contains("Anvil: That is way too heavy."), "{}", text);
+        assert!(
+            !text.contains("Crate") && !text.contains("Shelf"),
+            "{}",
+            text
+        );
+
+        let outcome = world.update_state(&parse("get all".to_string()));
+        assert_eq!(outcome.kind, OutcomeKind::Failure(FailureKind::TooHeavy));
+        assert!(!outcome.turn_consumed);
+    }
+
+    #[test]
+    fn get_all_with_nothing_to_take() {
+        let mut world = load(
+            r#"(id: "player", kind: Actor, labels: ["Yourself"], description: "you",
+                location: "store"),
+               (id: "store", kind: Location, labels: ["Store"], description: "a storeroom"),
+               (id: "panel", kind: Scenery, labels: ["Panel"], description: "a panel",
+                location: "store")"#,
+        )
+        .unwrap();
+        let outcome = world.update_state(&parse("get all".to_string()));
+        assert_eq!(outcome.kind, OutcomeKind::Failure(FailureKind::NotHere));
+    }
+
+    #[test]
+    fn directions_reverse_keeping_their_case() {
+        assert_eq!(opposite_direction("Aft"), "Forward");
+        assert_eq!(opposite_direction("starboard"), "port");
+        assert_eq!(opposite_direction("UP"), "Down");
+    }
+
+    #[test]
+    fn labels_with_no_opposite_are_kept() {
+        assert_eq!(opposite_direction("Airlock"), "Airlock");
+        assert_eq!(opposite_direction("hatch"), "hatch");
+    }
+
+    #[test]
+    fn passages_back_are_made_from_the_way_there() {
+        let bridge = object(
+            r#"(id: "bridge", kind: Location, labels: ["Bridge"], description: "the bridge")"#,
+        );
+        let aft = object(
+            r#"(id: "aft", kind: Passage, labels: ["Aft", "Hatch"], description: "a hatch",
+                location: "bridge", destination: "galley", reverse: Some(()))"#,
+        );
+        let back = aft.reversed(Some(&bridge)).unwrap();
+        assert_eq!(back.id, "aft_reverse");
+        assert_eq!(back.kind, Kind::Passage);
+        assert_eq!(back.labels, vec!["Forward", "Hatch"]);
+        assert_eq!(back.location, "galley");
+        assert_eq!(back.destination, "bridge");
+        assert_eq!(back.description, "a passage forward to the bridge");
+        assert_eq!(back.text_go, "You go forward to the bridge.");
+        assert!(back.reverse.is_none());
+
+        let one_way = object(
+            r#"(id: "chute", kind: Passage, labels: ["Down"], description: "a chute",
+                location: "bridge", destination: "galley")"#,
+        );
+        assert!(one_way.reversed(Some(&bridge)).is_none());
+    }
+
+    #[test]
+    fn passages_back_use_what_is_given() {
+        let airlock = object(
+            r#"(id: "airlock", kind: Passage, labels: ["Airlock"], description: "an airlock",
+                location: "bridge", destination: "outside",
+                reverse: Some((id: "inside", description: "the way in",
+                    text_go: "You cycle back in.")))"#,
+        );
+        let back = airlock.reversed(None).unwrap();
+        assert_eq!(back.id, "inside");
+        // Airlock has no opposite, so the way back has the same name
+        assert_eq!(back.labels, vec!["Airlock"]);
+        assert_eq!(back.description, "the way in");
+        assert_eq!(back.text_go, "You cycle back in.");
+    }
+
+    #[test]
+    fn two_way_passages_can_be_walked_both_ways() {
+        let mut world = load(
+            r#"(id: "player", kind: Actor, labels: ["Yourself"], description: "you",
+                location: "bridge", capacity: 5),
+               (id: "bridge", kind: Location, labels: ["Bridge"], description: "the bridge"),
+               (id: "galley", kind: Location, labels: ["Galley"], description: "the galley"),
+               (id: "aft", kind: Passage, labels: ["Aft"], description: "a passage aft",
+                location: "bridge", destination: "galley", reverse: Some(()))"#,
+        )
+        .unwrap();
+        let back = world.find_id("aft_reverse").unwrap();
+        assert_eq!(world[back].reverse_of, world.find_id("aft"));
+
+        assert!(world
+            .update_state(&parse("go aft".to_string()))
+            .is_success());
+        assert_eq!(world.player_location(), world.find_id("galley"));
+        assert!(world
+            .update_state(&parse("go forward".to_string()))
+            .is_success());
+        assert_eq!(world.player_location(), world.find_id("bridge"));
+    }
+
+    fn study() -> World {
+        load(
+            r#"(id: "player", kind: Actor, labels: ["Yourself"], description: "you",
+                location: "study", capacity: 5),
+               (id: "study", kind: Location, labels: ["Study"], description: "a study"),
+               (id: "desk", kind: Scenery, labels: ["Desk"], description: "a desk",
+                location: "study"),
+               (id: "note", kind: Item, labels: ["Note"], description: "a note",
+                location: "study", weight: 1, hidden: true, revealed_by: "desk",
+                text_found: "Under the desk you find a note."),
+               (id: "key", kind: Item, labels: ["Key"], description: "a brass key",
+                location: "study", weight: 1, hidden: true, revealed_by: "desk",
+                reveal: Examine),
+               (id: "coin", kind: Item, labels: ["Coin"], description: "a coin",
+                location: "study", weight: 1, hidden: true, revealed_by: "study",
+                reveal: Examine)"#,
+        )
+        .unwrap()
+    }
+
+    fn hidden(world: &World, id: &str) -> bool {
+        world[world.find_id(id).unwrap()].hidden
+    }
+
+    #[test]
+    fn hidden_things_cannot_be_named() {
+        let mut world = study();
+        assert!(!world.nouns_in_scope().contains(&"note".to_string()));
+        assert!(world.labels_meant_by("note").is_empty());
+        assert!(!world
+            .update_state(&parse("get note".to_string()))
+            .is_success());
+        assert!(!held(&world, "note"));
+    }
+
+    #[test]
+    fn examining_finds_only_what_looking_would() {
+        let mut world = study();
+        let outcome = world.update_state(&parse("examine desk".to_string()));
+        assert!(outcome.is_success());
+        assert!(outcome.to_string().contains("You find a brass key."));
+        assert!(!hidden(&world, "key") && hidden(&world, "note"));
+        assert!(outcome.changes.contains(&Change::ObjectRevealed {
+            object: world.find_id("key").unwrap()
+        }));
+
+        let outcome = world.update_state(&parse("look".to_string()));
+        assert!(outcome.to_string().contains("You find a coin."));
+        assert!(!hidden(&world, "coin"));
+    }
+
+    #[test]
+    fn searching_finds_everything_once() {
+        let mut world = study();
+        let outcome = world.update_state(&parse("search desk".to_string()));
+        let text = outcome.to_string();
+        assert!(text.contains("Under the desk you find a note."), "{}", text);
+        assert!(text.contains("You find a brass key."), "{}", text);
+        assert!(!hidden(&world, "note") && !hidden(&world, "key"));
+        // The coin is found by looking around, not by the desk
+        assert!(hidden(&world, "coin"));
+
+        let outcome = world.update_state(&parse("look under desk".to_string()));
+        assert_eq!(outcome.to_string(), "You search Desk but find nothing.\n");
+
+        assert!(world
+            .update_state(&parse("get note".to_string()))
+            .is_success());
+        assert!(held(&world, "note"));
+    }
+
+    fn lounge() -> World {
+        load(
+            r#"(id: "player", kind: Actor, labels: ["Yourself"], description: "you",
+                location: "lounge", capacity: 5),
+               (id: "lounge", kind: Location, labels: ["Lounge"], description: "a lounge"),
+               (id: "bar", kind: Scenery, labels: ["Bar"], description: "a bar",
+                location: "lounge"),
+               (id: "key", kind: Item, labels: ["Key"], description: "a key",
+                location: "lounge", weight: 1, hidden: true),
+               (id: "steward", kind: Actor, labels: ["Steward"], description: "a steward",
+                location: "lounge",
+                topics: [
+                    (about: ["Ship", "Vessel"], ask: "It is an old ship.", tell: "I know."),
+                    (about: ["Key"], when: "not steward.asked", ask: "What key?",
+                     effects: [Set("steward", "asked", "true")]),
+                    (about: ["Key"], ask: "Fine, take it.", effects: [Move("key", "player")]),
+                ],
+                dialogue: [
+                    (id: "greet", when: "not steward.met", text: "Welcome aboard.",
+                     effects: [Set("steward", "met", "true")],
+                     choices: [(text: "Where am I?", next: "where"), (text: "Goodbye.")]),
+                    (id: "again", text: "Back again?", choices: [(text: "Goodbye.")]),
+                    (id: "where", text: "On the Reentry.", effects: [Reveal("key")]),
+                ])"#,
+        )
+        .unwrap()
+    }
+
+    fn say(world: &mut World, input_str: &str) -> String {
+        let outcome = world.update_state(&parse(input_str.to_string()));
+        outcome.to_string()
+    }
+
+    #[test]
+    fn topics_answer_asking_and_telling() {
+        let mut world = lounge();
+        assert_eq!(
+            say(&mut world, "ask steward about vessel"),
+            "It is an old ship.\n"
+        );
+        assert_eq!(say(&mut world, "tell steward about ship"), "I know.\n");
+        assert_eq!(
+            say(&mut world, "ask steward about weather"),
+            "Steward has nothing to say about weather.\n"
+        );
+        assert_eq!(
+            say(&mut world, "tell steward about weather"),
+            "Steward shows no interest in weather.\n"
+        );
+        assert_eq!(
+            say(&mut world, "ask bar about ship"),
+            "You can't ask Bar.\n"
+        );
+    }
+
+    #[test]
+    fn topics_apply_when_their_condition_holds() {
+        let mut world = lounge();
+        assert_eq!(say(&mut world, "ask steward about key"), "What key?\n");
+        let steward = world.find_id("steward").unwrap();
+        assert_eq!(world[steward].properties["asked"], "true");
+        assert!(!held(&world, "key"));
+
+        assert_eq!(say(&mut world, "ask steward about key"), "Fine, take it.\n");
+        assert!(held(&world, "key"));
+    }
+
+    #[test]
+    fn dialogue_walks_through_the_choices() {
+        let mut world = lounge();
+        let steward = world.find_id("steward");
+        assert_eq!(
+            say(&mut world, "talk to steward"),
+            "Welcome aboard.\n1. Where am I?\n2. Goodbye.\n"
+        );
+        assert_eq!(world.conversation().map(|(actor, _)| actor), steward);
+
+        let outcome = world.update_state(&parse("3".to_string()));
+        assert_eq!(outcome.kind, OutcomeKind::Failure(FailureKind::NotAllowed));
+        assert_eq!(outcome.to_string(), "Please choose a number from 1 to 2.\n");
+
+        assert_eq!(
+            say(&mut world, "1"),
+            "You say, \"Where am I?\"\nOn the Reentry.\n"
+        );
+        assert!(!hidden(&world, "key"));
+        assert!(world.conversation().is_none());
+
+        // The greeting only holds the first time
+        assert_eq!(
+            say(&mut world, "talk to steward"),
+            "Back again?\n1. Goodbye.\n"
+        );
+        assert_eq!(say(&mut world, "1"), "You say, \"Goodbye.\"\n");
+        assert!(world.conversation().is_none());
+    }
+
+    #[test]
+    fn doing_anything_else_ends_a_conversation() {
+        let mut world = lounge();
+        say(&mut world, "talk to steward");
+        say(&mut world, "look");
+        assert!(world.conversation().is_none());
+        let outcome = world.update_state(&parse("1".to_string()));
+        assert_eq!(outcome.kind, OutcomeKind::Failure(FailureKind::NoActor));
+        assert_eq!(outcome.to_string(), "You are not talking to anyone.\n");
+    }
+
+    #[test]
+    fn actors_act_once_the_player_has_had_their_turn() {
+        let mut world = load(
+            r#"(id: "player", kind: Actor, labels: ["Yourself"], description: "you",
+                location: "bridge", capacity: 5),
+               (id: "bridge", kind: Location, labels: ["Bridge"], description: "the bridge"),
+               (id: "galley", kind: Location, labels: ["Galley"], description: "the galley"),
+               (id: "aft", kind: Passage, labels: ["Aft"], description: "a passage aft",
+                location: "bridge", destination: "galley", reverse: Some(())),
+               (id: "crumb", kind: Item, labels: ["Crumb"], description: "a crumb",
+                location: "galley", weight: 1),
+               (id: "cat", kind: Actor, labels: ["Cat"], description: "a cat",
+                location: "bridge", capacity: 1,
+                behaviours: [(action: Follow), (action: Take("crumb"))])"#,
+        )
+        .unwrap();
+        let cat = world.find_id("cat").unwrap();
+        let crumb = world.find_id("crumb").unwrap();
+
+        let text = say(&mut world, "go aft");
+        assert!(
+            text.ends_with("Cat follows you.\nCat picks up Crumb.\n"),
+            "{}",
+            text
+        );
+        assert_eq!(world[cat].location, world.find_id("galley"));
+        assert_eq!(world[crumb].location, Some(cat));
+
+        // Taking no turn gives the cat nothing to do
+        assert_eq!(say(&mut world, "inventory"), "You are empty handed.\n");
+        say(&mut world, "go forward");
+        assert_eq!(world[cat].location, world.find_id("bridge"));
+    }
+
+    fn hangar() -> World {
+        load(
+            r#"(id: "player", kind: Actor, labels: ["Yourself"], description: "you",
+                location: "hangar", capacity: 5),
+               (id: "hangar", kind: Location, labels: ["Hangar"], description: "a hangar"),
+               (id: "bay", kind: Location, labels: ["Bay"], description: "a bay"),
+               (id: "aft", kind: Passage, labels: ["Aft"], description: "a passage aft",
+                location: "hangar", destination: "bay", reverse: Some(())),
+               (id: "wrench", kind: Item, labels: ["Wrench"], description: "a wrench",
+                location: "hangar", weight: 1),
+               (id: "robot", kind: Actor, labels: ["Robot"], description: "a robot",
+                location: "hangar", capacity: 5, obeys: true),
+               (id: "pilot", kind: Actor, labels: ["Pilot"], description: "a pilot",
+                location: "hangar")"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn a_comma_starts_an_order() {
+        assert!(matches!(
+            parse("Robot, Go Aft".to_string()),
+            Command::Order(actor, order) if actor == "robot" && order == "Go Aft"
+        ));
+        assert!(matches!(
+            parse("robot,".to_string()),
+            Command::Order(actor, order) if actor == "robot" && order.is_empty()
+        ));
+        assert!(matches!(parse("go aft".to_string()), Command::Go(noun) if noun == "aft"));
+    }
+
+    #[test]
+    fn actors_that_obey_do_as_they_are_told() {
+        let mut world = hangar();
+        let robot = world.find_id("robot").unwrap();
+        let wrench = world.find_id("wrench").unwrap();
+
+        assert_eq!(
+            say(&mut world, "robot, get wrench"),
+            "Robot picks up Wrench.\n"
+        );
+        assert_eq!(world[wrench].location, Some(robot));
+        assert_eq!(
+            say(&mut world, "robot, give wrench"),
+            "You get Wrench from Robot.\n"
+        );
+        assert!(held(&world, "wrench"));
+
+        assert_eq!(say(&mut world, "robot, go aft"), "Robot goes aft.\n");
+        assert_eq!(world[robot].location, world.find_id("bay"));
+    }
+
+    #[test]
+    fn orders_that_cannot_be_carried_out() {
+        let mut world = hangar();
+        for (input_str, kind, text) in [
+            (
+                "pilot, go aft",
+                FailureKind::NotAllowed,
+                "Pilot ignores you.",
+            ),
+            (
+                "robot,",
+                FailureKind::UnknownCommand,
+                "What do you want Robot to do?",
+            ),
+            (
+                "robot, get pilot",
+                FailureKind::NotAllowed,
+                "Robot can't take that.",
+            ),
+            (
+                "robot, drop wrench",
+                FailureKind::NotHere,
+                "Robot can't find any wrench.",
+            ),
+            (
+                "robot, inventory",
+                FailureKind::NotAllowed,
+                "Robot can't inventory for you.",
+            ),
+        ] {
+            let outcome = world.update_state(&parse(input_str.to_string()));
+            assert_eq!(outcome.kind, OutcomeKind::Failure(kind), "{}", input_str);
+            assert_eq!(outcome.to_string(), format!("{}\n", text), "{}", input_str);
+        }
+        assert_eq!(
+            world[world.find_id("robot").unwrap()].location,
+            world.find_id("hangar")
+        );
+    }
+
+    #[test]
+    fn a_comma_after_anything_but_an_actor_is_not_an_order() {
+        let mut world = hangar();
+        let outcome = world.update_state(&parse("wrench, go aft".to_string()));
+        assert_eq!(
+            outcome.kind,
+            OutcomeKind::Failure(FailureKind::UnknownCommand)
+        );
+        assert_eq!(
+            outcome.to_string(),
+            "I don't know how to 'wrench, go aft'.\n"
+        );
+
+        // Nor is a hidden actor's name
+        let robot = world.find_id("robot").unwrap();
+        world.objects[robot.0].hidden = true;
+        let outcome = world.update_state(&parse("robot, go aft".to_string()));
+        assert_eq!(
+            outcome.kind,
+            OutcomeKind::Failure(FailureKind::UnknownCommand)
+        );
+    }
 }
@@ -0,0 +1,410 @@
+//
+// Reentry Analysis
+//
+// Explores every state a game can reach from its starting World to find
+// places and things the player can never get to, and to check that the
+// game can still be won.
+use super::behaviour::Action;
+use super::{parse, template, Distance, Kind, ObjectId, World};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+// The state of the game is where everything is, whether it is hidden,
+// what its properties are and how far along its patrols it has got, along
+// with any conversation under way
+type StateKey = (
+    Vec<(Option<ObjectId>, bool, BTreeMap<String, String>, Vec<usize>)>,
+    Option<(ObjectId, usize)>,
+);
+
+// Whether the game's goals can be met. Unknown when the search stopped
+// at its limit without finding a win.
+#[derive(Serialize, PartialEq, Eq, Clone, Copy, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum WinCondition {
+    NotDefined,
+    Reachable,
+    NotReachable,
+    Unknown,
+}
+
+// When the search is not complete, the unreachable locations and
+// unobtainable items are only those not found before it stopped
+#[derive(Debug)]
+pub struct Analysis {
+    pub states_explored: usize,
+    pub complete: bool,
+    pub unreachable_locations: Vec<ObjectId>,
+    pub unobtainable_items: Vec<ObjectId>,
+    pub win_condition: WinCondition,
+    pub winning_path: Option<Vec<String>>,
+    pub dead_ends: usize,
+    pub dead_end_path: Option<Vec<String>>,
+}
+
+fn state_key(world: &World) -> StateKey {
+    let objects = world
+        .iter()
+        .map(|(_, object)| {
+            let patrols = object
+                .behaviours
+                .iter()
+                .filter_map(|behaviour| match behaviour.action {
+                    Action::Patrol { next, .. } => Some(next),
+                    _ => None,
+                })
+                .collect();
+            (
+                object.location,
+                object.hidden,
+                object.properties.clone(),
+                patrols,
+            )
+        })
+        .collect();
+    (objects, world.conversation())
+}
+
+fn candidate_commands(world: &World) -> Vec<String> {
+    let mut commands: Vec<String> = Vec::new();
+
+    // Every reply on offer, as well as walking away
+    if let Some((actor, node)) = world.conversation() {
+        let choices = world[actor].dialogue[node]
+            .choices
+            .iter()
+            .filter(|choice| template::condition_holds(&choice.when, world))
+            .count();
+        commands.extend((1..=choices).map(|choice| choice.to_string()));
+    }
+
+    // Searching finds everything that looking would
+    let reveals = |revealer: ObjectId| {
+        world
+            .iter()
+            .any(|(_, object)| object.hidden && object.revealed_by == Some(revealer))
+    };
+    if world.player_location().is_some_and(reveals) {
+        commands.push("search".to_string());
+    }
+
+    for (pos, object) in world.iter() {
+        if object.hidden {
+            continue;
+        }
+        if reveals(pos) && world.get_distance(Some(world.player), Some(pos)) < Distance::OverThere {
+            for label in &object.labels {
+                commands.push(format!("search {}", label));
+            }
+        }
+
+        let verbs: &[&str] = match world.get_distance(Some(world.player), Some(pos)) {
+            Distance::Here if object.kind == Kind::Passage => &["go"],
+            Distance::Here | Distance::HereContained => &["get"],
+            Distance::Held | Distance::HeldContained => &["drop", "give"],
+            _ => &[],
+        };
+
+        for verb in verbs {
+            for label in &object.labels {
+                commands.push(format!("{} {}", verb, label));
+            }
+        }
+
+        // Only the first label of an actor, and the first way of naming
+        // a topic, as the others lead to the same state
+        if object.kind == Kind::Actor && pos != world.player {
+            if world.get_distance(Some(world.player), Some(pos)) != Distance::Here {
+                continue;
+            }
+            let actor = &object.labels[0];
+            if !object.dialogue.is_empty() {
+                commands.push(format!("talk to {}", actor));
+            }
+            for topic in &object.topics {
+                let Some(about) = topic.about.first() else {
+                    continue;
+                };
+                if !topic.ask.is_empty() {
+                    commands.push(format!("ask {} about {}", actor, about));
+                }
+                if !topic.tell.is_empty() {
+                    commands.push(format!("tell {} about {}", actor, about));
+                }
+            }
+            for &held in world.contents_of(pos) {
+                if !world[held].hidden {
+                    commands.push(format!("ask {} for {}", actor, world[held].labels[0]));
+                }
+            }
+
+            // Whatever an actor that obeys can be told to do where it is.
+            // Telling it to give something is the same as asking for it.
+            if !object.obeys {
+                continue;
+            }
+            let mut orders: Vec<(&str, ObjectId)> = Vec::new();
+            for &held in world.contents_of(pos) {
+                orders.push(("drop", held));
+            }
+            for &near in object
+                .location
+                .map_or(&[][..], |here| world.contents_of(here))
+            {
+                match world[near].kind {
+                    Kind::Passage => orders.push(("go", near)),
+                    Kind::Item if !world[near].is_fixed() => orders.push(("get", near)),
+                    _ => (),
+                }
+            }
+            for (verb, order) in orders {
+                if !world[order].hidden {
+                    commands.push(format!("{}, {} {}", actor, verb, world[order].labels[0]));
+                }
+            }
+        }
+    }
+    commands
+}
+
+fn path_to(parents: &[Option<(usize, String)>], mut state: usize) -> Vec<String> {
+    let mut path: Vec<String> = Vec::new();
+    while let Some((parent, command)) = &parents[state] {
+        path.push(command.to_string());
+        state = *parent;
+    }
+    path.reverse();
+    path
+}
+
+pub fn analyse(start: &World, max_states: usize) -> Analysis {
+    let mut seen: HashMap<StateKey, usize> = HashMap::new();
+    let mut parents: Vec<Option<(usize, String)>> = vec![None];
+    let mut edges: Vec<Vec<usize>> = vec![Vec::new()];
+    let mut won: Vec<bool> = vec![start.is_won()];
+    let mut frontier: VecDeque<(usize, World)> = VecDeque::new();
+
+    let mut visited_locations: Vec<bool> = vec![false; start.len()];
+    let mut held_items: Vec<bool> = vec![false; start.len()];
+    let mut complete = true;
+
+    seen.insert(state_key(start), 0);
+    frontier.push_back((0, start.clone()));
+
+    while let Some((state, world)) = frontier.pop_front() {
+        if let Some(location) = world.player_location() {
+            visited_locations[location.0] = true;
+        }
+        for (pos, object) in world.iter() {
+            if object.location == Some(world.player) {
+                held_items[pos.0] = true;
+            }
+        }
+
+        // The game ends as soon as it is won
+        if won[state] {
+            continue;
+        }
+
+        for command in candidate_commands(&world) {
+            let mut next_world = world.clone();
+            next_world.update_state(&parse(command.to_string()));
+
+            let key = state_key(&next_world);
+            let next_state = match seen.get(&key) {
+                Some(next_state) => *next_state,
+                None if seen.len() >= max_states => {
+                    complete = false;
+                    continue;
+                }
+                None => {
+                    let next_state = parents.len();
+                    seen.insert(key, next_state);
+                    parents.push(Some((state, command)));
+                    edges.push(Vec::new());
+                    won.push(next_world.is_won());
+                    frontier.push_back((next_state, next_world));
+                    next_state
+                }
+            };
+
+            if next_state != state && !edges[state].contains(&next_state) {
+                edges[state].push(next_state);
+            }
+        }
+    }
+
+    let unreachable_locations: Vec<ObjectId> = start
+        .iter()
+        .filter(|(pos, object)| object.kind == Kind::Location && !visited_locations[pos.0])
+        .map(|(pos, _)| pos)
+        .collect();
+
+    // Only report things that the player could carry
+    let unobtainable_items: Vec<ObjectId> = start
+        .iter()
+        .filter(|(pos, object)| {
+            *pos != start.player
+                && object.kind == Kind::Item
+                && !object.is_fixed()
+                && object.weight <= start[start.player].capacity
+                && !held_items[pos.0]
+        })
+        .map(|(pos, _)| pos)
+        .collect();
+
+    let mut analysis = Analysis {
+        states_explored: parents.len(),
+        complete,
+        unreachable_locations,
+        unobtainable_items,
+        win_condition: WinCondition::NotDefined,
+        winning_path: None,
+        dead_ends: 0,
+        dead_end_path: None,
+    };
+
+    if start.goals.is_empty() {
+        return analysis;
+    }
+
+    // States are numbered in the order they were found, so the first
+    // winning state has the shortest path
+    let first_win = won.iter().position(|state_won| *state_won);
+    analysis.win_condition = match (first_win, complete) {
+        (Some(_), _) => WinCondition::Reachable,
+        (None, true) => WinCondition::NotReachable,
+        (None, false) => WinCondition::Unknown,
+    };
+    analysis.winning_path = first_win.map(|state| path_to(&parents, state));
+
+    // Dead ends are only known once every state has been found, and
+    // only mean something if the game can be won at all
+    if complete && first_win.is_some() {
+        let mut reverse_edges: Vec<Vec<usize>> = vec![Vec::new(); edges.len()];
+        for (state, next_states) in edges.iter().enumerate() {
+            for next_state in next_states {
+                reverse_edges[*next_state].push(state);
+            }
+        }
+
+        let mut can_win: Vec<bool> = won.clone();
+        let mut queue: VecDeque<usize> = (0..won.len()).filter(|state| won[*state]).collect();
+        while let Some(state) = queue.pop_front() {
+            for prev_state in &reverse_edges[state] {
+                if !can_win[*prev_state] {
+                    can_win[*prev_state] = true;
+                    queue.push_back(*prev_state);
+                }
+            }
+        }
+
+        analysis.dead_ends = can_win.iter().filter(|state| !**state).count();
+        analysis.dead_end_path = can_win
+            .iter()
+            .position(|state| !*state)
+            .map(|state| path_to(&parents, state));
+    }
+
+    analysis
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rlib::SavedWorld;
+
+    // The bridge and galley are joined both ways, and a drop leads from
+    // the bridge into a pit there is no way out of. The pen waits in the
+    // galley and nothing leads to the vault.
+    fn world(goals: &str) -> World {
+        let text = format!(
+            r#"(objects: [
+                (id: "player", kind: Actor, labels: ["Yourself"], description: "you",
+                 location: "bridge", capacity: 10),
+                (id: "bridge", kind: Location, labels: ["Bridge"], description: "the bridge"),
+                (id: "galley", kind: Location, labels: ["Galley"], description: "the galley"),
+                (id: "pit", kind: Location, labels: ["Pit"], description: "a pit"),
+                (id: "vault", kind: Location, labels: ["Vault"], description: "a vault"),
+                (id: "bridge_aft", kind: Passage, labels: ["Aft"], description: "a passage",
+                 location: "bridge", destination: "galley"),
+                (id: "galley_forward", kind: Passage, labels: ["Forward"],
+                 description: "a passage", location: "galley", destination: "bridge"),
+                (id: "bridge_down", kind: Passage, labels: ["Down"], description: "a drop",
+                 location: "bridge", destination: "pit"),
+                (id: "pen", kind: Item, labels: ["Pen"], description: "a pen", location: "galley",
+                 weight: 1),
+                (id: "gold", kind: Item, labels: ["Gold"], description: "gold", location: "vault",
+                 weight: 1),
+                (id: "table", kind: Scenery, labels: ["Table"], description: "a table",
+                 location: "galley"),
+             ],
+             goals: [{}])"#,
+            goals
+        );
+        let saved: SavedWorld = ron::from_str(&text).unwrap();
+        saved.try_into().unwrap()
+    }
+
+    fn labels(world: &World, objects: &[ObjectId]) -> Vec<String> {
+        objects
+            .iter()
+            .map(|&pos| world[pos].labels[0].to_string())
+            .collect()
+    }
+
+    #[test]
+    fn unreachable_places_and_things_are_found() {
+        let world = world("");
+        let analysis = analyse(&world, 1000);
+        assert!(analysis.complete);
+        assert_eq!(
+            labels(&world, &analysis.unreachable_locations),
+            vec!["Vault"]
+        );
+        assert_eq!(labels(&world, &analysis.unobtainable_items), vec!["Gold"]);
+        assert_eq!(analysis.win_condition, WinCondition::NotDefined);
+    }
+
+    #[test]
+    fn shortest_win_and_dead_ends_are_found() {
+        let analysis = analyse(&world(r#"(object: "pen", location: "player")"#), 1000);
+        assert_eq!(analysis.win_condition, WinCondition::Reachable);
+        let path: Vec<String> = analysis
+            .winning_path
+            .unwrap()
+            .iter()
+            .map(|command| command.to_lowercase())
+            .collect();
+        assert_eq!(path, vec!["go aft", "get pen"]);
+
+        // Falling into the pit, with or without the pen
+        assert!(analysis.dead_ends > 0);
+        assert_eq!(
+            analysis
+                .dead_end_path
+                .unwrap()
+                .last()
+                .unwrap()
+                .to_lowercase(),
+            "go down"
+        );
+    }
+
+    #[test]
+    fn goals_out_of_reach_are_reported() {
+        let analysis = analyse(&world(r#"(object: "table", location: "player")"#), 1000);
+        assert!(analysis.complete);
+        assert_eq!(analysis.win_condition, WinCondition::NotReachable);
+        assert_eq!(analysis.winning_path, None);
+    }
+
+    #[test]
+    fn cut_short_searches_do_not_know() {
+        let analysis = analyse(&world(r#"(object: "table", location: "player")"#), 2);
+        assert!(!analysis.complete);
+        assert_eq!(analysis.states_explored, 2);
+        assert_eq!(analysis.win_condition, WinCondition::Unknown);
+        assert_eq!(analysis.dead_ends, 0);
+    }
+}
@@ -0,0 +1,69 @@
+//
+// Reentry Batch
+//
+// Plays a list of commands through a Session without anyone at the
+// keyboard. A run stops at the first command that fails or once the game
+// is over, and says which of those happened, so scripted runs can tell a
+// broken game from a finished one.
+use super::outcome::FailureKind;
+use super::session::Session;
+use super::stream::InputSource;
+
+#[derive(PartialEq, Eq, Debug)]
+pub enum Ending {
+    // Every command was played and the game is still going
+    OutOfCommands,
+    Failed {
+        command: usize,
+        input: String,
+        kind: FailureKind,
+    },
+    Won,
+    Quit,
+}
+
+#[derive(Debug)]
+pub struct BatchRun {
+    // Each command after a '>' prompt, followed by what the game said
+    pub output: String,
+    pub commands_run: usize,
+    pub ending: Ending,
+}
+
+// Blank lines and lines starting with '#' are skipped
+pub fn run(session: &mut Session, commands: &mut impl InputSource) -> BatchRun {
+    let mut output = String::new();
+    let mut commands_run: usize = 0;
+
+    while let Some(input_str) = commands.read_line() {
+        if input_str.trim().is_empty() || input_str.trim_start().starts_with('#') {
+            continue;
+        }
+
+        let response = session.step(&input_str);
+        commands_run += 1;
+        output += &format!("> {}\n{}\n", input_str.trim(), response.output);
+
+        let ending = match (response.failure, response.game_over) {
+            (Some(kind), _) => Ending::Failed {
+                command: commands_run,
+                input: input_str.trim().to_string(),
+                kind,
+            },
+            (None, true) if session.world.is_won() => Ending::Won,
+            (None, true) => Ending::Quit,
+            (None, false) => continue,
+        };
+        return BatchRun {
+            output,
+            commands_run,
+            ending,
+        };
+    }
+
+    BatchRun {
+        output,
+        commands_run,
+        ending: Ending::OutOfCommands,
+    }
+}
@@ -0,0 +1,137 @@
+//
+// Reentry Behaviour
+//
+// What actors do by themselves. After every turn that the player uses up,
+// each actor tries its behaviours in order: patrolling a route of passages,
+// following the player, greeting the player on the way in, or picking up
+// and dropping things. A behaviour only acts when its condition holds,
+// written as in a template's {if}, and an actor moves at most once a turn.
+use super::{ObjectId, ParseError, SavedObject, SavedWorld};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub enum SavedAction {
+    // Takes each passage in the route in turn, starting over at the end.
+    // next is how far along the route the actor has got.
+    Patrol {
+        route: Vec<String>,
+        #[serde(default, skip_serializing_if = "is_route_start")]
+        next: usize,
+    },
+    // Goes wherever the player goes from where the actor is
+    Follow,
+    // Says its text when the player comes to where the actor is
+    Greet,
+    // Picks up an object lying where the actor is
+    Take(String),
+    // Drops an object the actor is holding
+    Drop(String),
+}
+
+pub fn is_route_start(value: &usize) -> bool {
+    *value == 0
+}
+
+// Text replaces what the player is told when they see the actor act
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct SavedBehaviour {
+    pub action: SavedAction,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub when: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub text: String,
+}
+
+#[derive(Clone, Debug)]
+pub enum Action {
+    Patrol { route: Vec<ObjectId>, next: usize },
+    Follow,
+    Greet,
+    Take(ObjectId),
+    Drop(ObjectId),
+}
+
+#[derive(Clone, Debug)]
+pub struct Behaviour {
+    pub action: Action,
+    pub when: String,
+    pub text: String,
+}
+
+impl SavedAction {
+    // As SavedEffect::names
+    pub fn names(&self) -> Vec<(&'static str, &str)> {
+        match self {
+            SavedAction::Patrol { route, .. } => route
+                .iter()
+                .map(|name| ("patrol route", name.as_str()))
+                .collect(),
+            SavedAction::Follow | SavedAction::Greet => Vec::new(),
+            SavedAction::Take(object) => vec![("take object", object)],
+            SavedAction::Drop(object) => vec![("drop object", object)],
+        }
+    }
+}
+
+pub(super) fn resolve_behaviours(
+    ids: &HashMap<&str, ObjectId>,
+    item: &SavedObject,
+    errors: &mut Vec<ParseError>,
+) -> Vec<Behaviour> {
+    let mut behaviours: Vec<Behaviour> = Vec::new();
+    for behaviour in &item.behaviours {
+        let names = behaviour.action.names();
+        let resolved: Vec<ObjectId> = names
+            .iter()
+            .filter_map(|(field, name)| {
+                SavedWorld::resolve_required_name(ids, item, field, name, errors)
+            })
+            .collect();
+        if resolved.len() != names.len() {
+            continue;
+        }
+
+        let action = match &behaviour.action {
+            SavedAction::Patrol { route, next } => Action::Patrol {
+                // Wherever a saved game had got to, as long as it is on the route
+                next: if *next < route.len() { *next } else { 0 },
+                route: resolved,
+            },
+            SavedAction::Follow => Action::Follow,
+            SavedAction::Greet => Action::Greet,
+            SavedAction::Take(_) => Action::Take(resolved[0]),
+            SavedAction::Drop(_) => Action::Drop(resolved[0]),
+        };
+        behaviours.push(Behaviour {
+            action,
+            when: behaviour.when.to_string(),
+            text: behaviour.text.to_string(),
+        });
+    }
+    behaviours
+}
+
+pub(super) fn saved_behaviours(
+    behaviours: &[Behaviour],
+    id_of: &impl Fn(ObjectId) -> String,
+) -> Vec<SavedBehaviour> {
+    behaviours
+        .iter()
+        .map(|behaviour| SavedBehaviour {
+            action: match &behaviour.action {
+                Action::Patrol { route, next } => SavedAction::Patrol {
+                    route: route.iter().map(|passage| id_of(*passage)).collect(),
+                    next: *next,
+                },
+                Action::Follow => SavedAction::Follow,
+                Action::Greet => SavedAction::Greet,
+                Action::Take(object) => SavedAction::Take(id_of(*object)),
+                Action::Drop(object) => SavedAction::Drop(id_of(*object)),
+            },
+            when: behaviour.when.to_string(),
+            text: behaviour.text.to_string(),
+        })
+        .collect()
+}
@@ -0,0 +1,281 @@
+//
+// Reentry Dialogue
+//
+// What actors have to say. Topics answer 'ask ACTOR about TOPIC' and
+// 'tell ACTOR about TOPIC', and a dialogue is a tree of nodes that 'talk to
+// ACTOR' walks through, the player picking each reply by number. Topics
+// and nodes only apply when their condition holds, written as in a
+// template's {if}, and change the World through their effects.
+use super::{ObjectId, ParseError, SavedObject, SavedWorld};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// Effects name objects by id, as everything in a game file does
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub enum SavedEffect {
+    // Moves an object, whatever it weighs
+    Move(String, String),
+    // Sets one of an object's properties
+    Set(String, String, String),
+    // Reveals a hidden object
+    Reveal(String),
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct SavedTopic {
+    pub about: Vec<String>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub ask: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub tell: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub when: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub effects: Vec<SavedEffect>,
+}
+
+// A choice with no next node ends the conversation
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct SavedChoice {
+    pub text: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub next: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub when: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct SavedNode {
+    pub id: String,
+    pub text: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub when: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub effects: Vec<SavedEffect>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub choices: Vec<SavedChoice>,
+}
+
+#[derive(Clone, Debug)]
+pub enum Effect {
+    Move {
+        object: ObjectId,
+        to: ObjectId,
+    },
+    Set {
+        object: ObjectId,
+        property: String,
+        value: String,
+    },
+    Reveal {
+        object: ObjectId,
+    },
+}
+
+#[derive(Clone, Debug)]
+pub struct Topic {
+    pub about: Vec<String>,
+    pub ask: String,
+    pub tell: String,
+    pub when: String,
+    pub effects: Vec<Effect>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Choice {
+    pub text: String,
+    pub next: Option<usize>,
+    pub when: String,
+}
+
+#[derive(Clone, Debug)]
+pub struct Node {
+    pub id: String,
+    pub text: String,
+    pub when: String,
+    pub effects: Vec<Effect>,
+    pub choices: Vec<Choice>,
+}
+
+impl Topic {
+    // topic is already in lower case
+    pub fn is_about(&self, topic: &str) -> bool {
+        self.about.iter().any(|about| about.to_lowercase() == topic)
+    }
+}
+
+impl SavedEffect {
+    // Every object the effect names, with what it names it as
+    pub fn names(&self) -> Vec<(&'static str, &str)> {
+        match self {
+            SavedEffect::Move(object, to) => vec![("effect object", object), ("effect to", to)],
+            SavedEffect::Set(object, _, _) => vec![("effect object", object)],
+            SavedEffect::Reveal(object) => vec![("effect object", object)],
+        }
+    }
+
+    fn resolve(
+        &self,
+        ids: &HashMap<&str, ObjectId>,
+        item: &SavedObject,
+        errors: &mut Vec<ParseError>,
+    ) -> Option<Effect> {
+        let mut resolve = |field: &str, name: &str| {
+            SavedWorld::resolve_required_name(ids, item, field, name, errors)
+        };
+
+        match self {
+            SavedEffect::Move(object, to) => {
+                let object = resolve("effect object", object);
+                let to = resolve("effect to", to);
+                Some(Effect::Move {
+                    object: object?,
+                    to: to?,
+                })
+            }
+            SavedEffect::Set(object, property, value) => Some(Effect::Set {
+                object: resolve("effect object", object)?,
+                property: property.to_lowercase(),
+                value: value.to_string(),
+            }),
+            SavedEffect::Reveal(object) => Some(Effect::Reveal {
+                object: resolve("effect object", object)?,
+            }),
+        }
+    }
+}
+
+fn resolve_effects(
+    effects: &[SavedEffect],
+    ids: &HashMap<&str, ObjectId>,
+    item: &SavedObject,
+    errors: &mut Vec<ParseError>,
+) -> Vec<Effect> {
+    effects
+        .iter()
+        .filter_map(|effect| effect.resolve(ids, item, errors))
+        .collect()
+}
+
+fn saved_effects(effects: &[Effect], id_of: &impl Fn(ObjectId) -> String) -> Vec<SavedEffect> {
+    effects
+        .iter()
+        .map(|effect| match effect {
+            Effect::Move { object, to } => SavedEffect::Move(id_of(*object), id_of(*to)),
+            Effect::Set {
+                object,
+                property,
+                value,
+            } => SavedEffect::Set(id_of(*object), property.to_string(), value.to_string()),
+            Effect::Reveal { object } => SavedEffect::Reveal(id_of(*object)),
+        })
+        .collect()
+}
+
+pub(super) fn resolve_topics(
+    ids: &HashMap<&str, ObjectId>,
+    item: &SavedObject,
+    errors: &mut Vec<ParseError>,
+) -> Vec<Topic> {
+    item.topics
+        .iter()
+        .map(|topic| Topic {
+            about: topic.about.clone(),
+            ask: topic.ask.to_string(),
+            tell: topic.tell.to_string(),
+            when: topic.when.to_string(),
+            effects: resolve_effects(&topic.effects, ids, item, errors),
+        })
+        .collect()
+}
+
+// Choices lead to other nodes of the same actor's dialogue by node id
+pub(super) fn resolve_dialogue(
+    ids: &HashMap<&str, ObjectId>,
+    item: &SavedObject,
+    errors: &mut Vec<ParseError>,
+) -> Vec<Node> {
+    let mut nodes: Vec<Node> = Vec::new();
+    for node in &item.dialogue {
+        let mut choices: Vec<Choice> = Vec::new();
+        for choice in &node.choices {
+            let next = match choice.next.is_empty() {
+                true => None,
+                false => {
+                    let next = item
+                        .dialogue
+                        .iter()
+                        .position(|other| other.id == choice.next);
+                    if next.is_none() {
+                        errors.push(ParseError::UnknownName {
+                            object: item.id.to_string(),
+                            field: "dialogue next".into(),
+                            name: choice.next.to_string(),
+                        });
+                    }
+                    next
+                }
+            };
+            choices.push(Choice {
+                text: choice.text.to_string(),
+                next,
+                when: choice.when.to_string(),
+            });
+        }
+
+        nodes.push(Node {
+            id: node.id.to_string(),
+            text: node.text.to_string(),
+            when: node.when.to_string(),
+            effects: resolve_effects(&node.effects, ids, item, errors),
+            choices,
+        });
+    }
+    nodes
+}
+
+pub(super) fn saved_topics(
+    topics: &[Topic],
+    id_of: &impl Fn(ObjectId) -> String,
+) -> Vec<SavedTopic> {
+    topics
+        .iter()
+        .map(|topic| SavedTopic {
+            about: topic.about.clone(),
+            ask: topic.ask.to_string(),
+            tell: topic.tell.to_string(),
+            when: topic.when.to_string(),
+            effects: saved_effects(&topic.effects, id_of),
+        })
+        .collect()
+}
+
+pub(super) fn saved_dialogue(
+    nodes: &[Node],
+    id_of: &impl Fn(ObjectId) -> String,
+) -> Vec<SavedNode> {
+    nodes
+        .iter()
+        .map(|node| SavedNode {
+            id: node.id.to_string(),
+            text: node.text.to_string(),
+            when: node.when.to_string(),
+            effects: saved_effects(&node.effects, id_of),
+            choices: node
+                .choices
+                .iter()
+                .map(|choice| SavedChoice {
+                    text: choice.text.to_string(),
+                    next: choice
+                        .next
+                        .map(|next| nodes[next].id.to_string())
+                        .unwrap_or_default(),
+                    when: choice.when.to_string(),
+                })
+                .collect(),
+        })
+        .collect()
+}
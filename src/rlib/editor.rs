@@ -0,0 +1,118 @@
+//
+// Reentry Line Editor
+//
+// Keyboard input with line editing, a history that is kept between games,
+// and tab completion. The first word completes to a verb, and the rest to
+// the name of something the player can see from where they are.
+use super::stream::InputSource;
+use super::VERBS;
+use rustyline::completion::Completer;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::DefaultHistory;
+use rustyline::validate::Validator;
+use rustyline::{Context, Editor, Helper};
+use std::path::PathBuf;
+
+pub const PROMPT: &str = "> ";
+
+#[derive(Default)]
+struct GameHelper {
+    nouns: Vec<String>,
+}
+
+impl Completer for GameHelper {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        let before = &line[..pos];
+
+        let (start, words): (usize, Vec<&str>) = match before.find(char::is_whitespace) {
+            None => (0, VERBS.to_vec()),
+            Some(verb_end) => {
+                let start = before[verb_end..]
+                    .find(|ch: char| !ch.is_whitespace())
+                    .map_or(pos, |offset| verb_end + offset);
+                (start, self.nouns.iter().map(String::as_str).collect())
+            }
+        };
+
+        let partial = before[start..].to_lowercase();
+        let candidates = words
+            .into_iter()
+            .filter(|word| word.starts_with(&partial))
+            .map(String::from)
+            .collect();
+        Ok((start, candidates))
+    }
+}
+
+impl Hinter for GameHelper {
+    type Hint = String;
+}
+
+impl Highlighter for GameHelper {}
+
+impl Validator for GameHelper {}
+
+impl Helper for GameHelper {}
+
+pub struct EditorInput {
+    editor: Editor<GameHelper, DefaultHistory>,
+    history_file: Option<PathBuf>,
+}
+
+impl EditorInput {
+    // History is loaded from and saved to history_file when there is one
+    pub fn new(history_file: Option<PathBuf>) -> rustyline::Result<EditorInput> {
+        let mut editor = Editor::new()?;
+        editor.set_helper(Some(GameHelper::default()));
+        if let Some(history_file) = &history_file {
+            // There is no history yet the first time the game is played
+            let _ = editor.load_history(history_file);
+        }
+
+        Ok(EditorInput {
+            editor,
+            history_file,
+        })
+    }
+}
+
+impl InputSource for EditorInput {
+    fn read_line(&mut self) -> Option<String> {
+        // End of input and ^C both end the game
+        let input_str = self.editor.readline(PROMPT).ok()?;
+        if !input_str.trim().is_empty() {
+            let _ = self.editor.add_history_entry(input_str.as_str());
+        }
+        Some(input_str)
+    }
+
+    fn is_interactive(&self) -> bool {
+        true
+    }
+
+    fn shows_prompt(&self) -> bool {
+        true
+    }
+
+    fn set_nouns(&mut self, nouns: Vec<String>) {
+        if let Some(helper) = self.editor.helper_mut() {
+            helper.nouns = nouns;
+        }
+    }
+}
+
+impl Drop for EditorInput {
+    fn drop(&mut self) {
+        if let Some(history_file) = &self.history_file {
+            let _ = self.editor.save_history(history_file);
+        }
+    }
+}
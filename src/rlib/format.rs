@@ -0,0 +1,233 @@
+//
+// Reentry Formatting
+//
+// Lays out game text for display. Markup is parsed into styled characters,
+// long lines are wrapped between words by their visible width, indentation
+// is carried onto wrapped lines, and runs of blank lines are collapsed into
+// a single blank line.
+use super::markup::{self, Style, NBSP};
+use terminal_size::{terminal_size, Width};
+
+const DEF_WIDTH: usize = 80;
+
+type StyledLine = Vec<(char, Style)>;
+
+// The width of the terminal, or a sensible default when there isn't one
+pub fn terminal_width() -> usize {
+    match terminal_size() {
+        Some((Width(width), _)) if width > 0 => width as usize,
+        _ => DEF_WIDTH,
+    }
+}
+
+fn styled_lines(text: &str) -> Vec<StyledLine> {
+    let mut lines: Vec<StyledLine> = vec![Vec::new()];
+    for span in markup::parse(text) {
+        for ch in span.text.chars() {
+            match ch {
+                '\n' => lines.push(Vec::new()),
+                '\r' => (),
+                _ => lines.last_mut().unwrap().push((ch, span.style)),
+            }
+        }
+    }
+    lines
+}
+
+fn render_line(line: &[(char, Style)], color: bool, output: &mut String) {
+    let mut current = Style::default();
+    for &(ch, style) in line {
+        if color && style != current {
+            if !current.is_plain() {
+                output.push_str(current.ansi_end());
+            }
+            if !style.is_plain() {
+                output.push_str(&style.ansi_start());
+            }
+            current = style;
+        }
+        output.push(if ch == NBSP { ' ' } else { ch });
+    }
+    if color && !current.is_plain() {
+        output.push_str(current.ansi_end());
+    }
+    output.push('\n');
+}
+
+fn wrap_line(line: &[(char, Style)], width: usize) -> Vec<StyledLine> {
+    let indent_len = line.iter().take_while(|(ch, _)| *ch == ' ').count();
+    let indent = &line[..indent_len];
+    let mut wrapped: Vec<StyledLine> = Vec::new();
+    let mut current = indent.to_vec();
+    let mut has_words = false;
+
+    // Only plain spaces break lines, so non-breaking spaces hold words together
+    for word in line[indent_len..]
+        .split(|(ch, _)| *ch == ' ')
+        .filter(|word| !word.is_empty())
+    {
+        if has_words && current.len() + 1 + word.len() > width {
+            wrapped.push(current);
+            current = indent.to_vec();
+            has_words = false;
+        }
+
+        // A space inside a styled run keeps the style, so headings stay
+        // underlined from one word to the next
+        if has_words {
+            let style = match (current.last(), word.first()) {
+                (Some((_, before)), Some((_, after))) if before == after => *before,
+                _ => Style::default(),
+            };
+            current.push((' ', style));
+        }
+        current.extend_from_slice(word);
+        has_words = true;
+    }
+
+    wrapped.push(current);
+    wrapped
+}
+
+// A width of 0 leaves lines as they are. Without colour the markup is
+// simply dropped.
+pub fn format_text(text: &str, width: usize, color: bool) -> String {
+    let mut output = String::new();
+    let mut blank_pending = false;
+
+    for mut line in styled_lines(text) {
+        while line
+            .last()
+            .is_some_and(|(ch, _)| ch.is_whitespace() && *ch != NBSP)
+        {
+            line.pop();
+        }
+
+        if line.is_empty() {
+            blank_pending = !output.is_empty();
+            continue;
+        }
+
+        if blank_pending {
+            output.push('\n');
+            blank_pending = false;
+        }
+
+        if width == 0 {
+            render_line(&line, color, &mut output);
+        } else {
+            for wrapped in wrap_line(&line, width) {
+                render_line(&wrapped, color, &mut output);
+            }
+        }
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wrapped(text: &str, width: usize) -> Vec<String> {
+        let line = styled_lines(text).remove(0);
+        wrap_line(&line, width)
+            .iter()
+            .map(|wrapped| wrapped.iter().map(|(ch, _)| *ch).collect())
+            .collect()
+    }
+
+    #[test]
+    fn short_lines_are_left_alone() {
+        assert_eq!(wrapped("fits in ten", 11), vec!["fits in ten"]);
+    }
+
+    #[test]
+    fn long_lines_wrap_between_words() {
+        assert_eq!(
+            wrapped("the quick brown fox jumps", 10),
+            vec!["the quick", "brown fox", "jumps"]
+        );
+    }
+
+    #[test]
+    fn long_words_get_a_line_to_themselves() {
+        assert_eq!(
+            wrapped("a cryochamber door", 5),
+            vec!["a", "cryochamber", "door"]
+        );
+    }
+
+    #[test]
+    fn wrapped_lines_keep_their_indent() {
+        assert_eq!(
+            wrapped("  a glossy photo of a family", 12),
+            vec!["  a glossy", "  photo of a", "  family"]
+        );
+    }
+
+    #[test]
+    fn non_breaking_spaces_hold_words_together() {
+        assert_eq!(
+            wrapped("see deck[nbsp]12 now", 8),
+            vec!["see", "deck\u{a0}12", "now"]
+        );
+    }
+
+    #[test]
+    fn width_is_counted_in_characters() {
+        assert_eq!(wrapped("café au lait", 7), vec!["café au", "lait"]);
+    }
+
+    #[test]
+    fn markup_is_measured_by_what_shows() {
+        assert_eq!(
+            wrapped("[b]bold[/b] [i]words[/i] here", 10),
+            vec!["bold words", "here"]
+        );
+    }
+
+    #[test]
+    fn width_zero_does_not_wrap() {
+        let text = "a line that is much longer than any width";
+        assert_eq!(format_text(text, 0, false), format!("{}\n", text));
+    }
+
+    #[test]
+    fn every_line_is_wrapped() {
+        assert_eq!(
+            format_text("one two three\nfour five six", 9, false),
+            "one two\nthree\nfour five\nsix\n"
+        );
+    }
+
+    #[test]
+    fn blank_lines_collapse_and_trailing_space_goes() {
+        assert_eq!(
+            format_text("one   \n\n\n\ntwo\n\n", 0, false),
+            "one\n\ntwo\n"
+        );
+    }
+
+    #[test]
+    fn leading_blank_lines_are_dropped() {
+        assert_eq!(format_text("\n\none\n", 0, false), "one\n");
+    }
+
+    #[test]
+    fn colour_is_only_shown_when_asked_for() {
+        assert_eq!(format_text("[b]hi[/b] there", 0, false), "hi there\n");
+        assert_eq!(
+            format_text("[b]hi[/b] there", 0, true),
+            "\x1b[1mhi\x1b[0m there\n"
+        );
+    }
+
+    #[test]
+    fn styled_runs_keep_their_style_across_wrapped_spaces() {
+        let line = styled_lines("[h]Cargo Bay[/h]").remove(0);
+        let lines = wrap_line(&line, 20);
+        assert_eq!(lines.len(), 1);
+        assert!(lines[0].iter().all(|(_, style)| style.underline));
+    }
+}
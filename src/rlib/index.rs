@@ -0,0 +1,157 @@
+//
+// Reentry Index
+//
+// Lookups into a World that would otherwise scan every object: what each
+// object holds, which objects answer to a label, which object has an id,
+// what searching each object finds and which objects act by themselves.
+// The World keeps its index up to date as things move, so it must never be
+// changed behind the World's back.
+use super::{Object, ObjectId};
+use std::collections::HashMap;
+
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct WorldIndex {
+    // Kept in object order, so lists come out the way the game file has them
+    children: Vec<Vec<ObjectId>>,
+    labels: HashMap<String, Vec<ObjectId>>,
+    ids: HashMap<String, ObjectId>,
+    // Hidden objects by what reveals them, whether still hidden or not
+    revealed_by: HashMap<ObjectId, Vec<ObjectId>>,
+    // Objects with behaviours, which never gain or lose them
+    actors: Vec<ObjectId>,
+}
+
+impl WorldIndex {
+    pub fn new(objects: &[Object]) -> WorldIndex {
+        let mut index = WorldIndex {
+            children: vec![Vec::new(); objects.len()],
+            labels: HashMap::new(),
+            ids: HashMap::new(),
+            revealed_by: HashMap::new(),
+            actors: Vec::new(),
+        };
+
+        for (pos, object) in objects.iter().enumerate() {
+            let id = ObjectId(pos);
+            if let Some(location) = object.location {
+                index.children[location.0].push(id);
+            }
+            for label in &object.labels {
+                let with_label = index.labels.entry(label.to_lowercase()).or_default();
+                // An object may list the same label twice
+                if with_label.last() != Some(&id) {
+                    with_label.push(id);
+                }
+            }
+            index.ids.entry(object.id.to_string()).or_insert(id);
+            if let Some(revealer) = object.revealed_by {
+                index.revealed_by.entry(revealer).or_default().push(id);
+            }
+            if !object.behaviours.is_empty() {
+                index.actors.push(id);
+            }
+        }
+        index
+    }
+
+    pub fn children(&self, container: ObjectId) -> &[ObjectId] {
+        &self.children[container.0]
+    }
+
+    // Labels are matched in lower case, as the parser hands them over
+    pub fn with_label(&self, noun: &str) -> &[ObjectId] {
+        self.labels.get(noun).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn revealed_by(&self, revealer: ObjectId) -> &[ObjectId] {
+        self.revealed_by.get(&revealer).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn actors(&self) -> &[ObjectId] {
+        &self.actors
+    }
+
+    pub fn find_id(&self, id: &str) -> Option<ObjectId> {
+        self.ids.get(id).copied()
+    }
+
+    pub fn moved(&mut self, object: ObjectId, from: Option<ObjectId>, to: Option<ObjectId>) {
+        if let Some(from) = from {
+            let siblings = &mut self.children[from.0];
+            if let Ok(pos) = siblings.binary_search(&object) {
+                siblings.remove(pos);
+            }
+        }
+        if let Some(to) = to {
+            let siblings = &mut self.children[to.0];
+            if let Err(pos) = siblings.binary_search(&object) {
+                siblings.insert(pos, object);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rlib::{parse, Distance, SavedWorld, World};
+
+    fn game() -> World {
+        let saved: SavedWorld = ron::from_str(include_str!("../game_file.ron")).unwrap();
+        saved.try_into().unwrap()
+    }
+
+    // What nouns_in_scope found before there was an index
+    fn scanned_nouns(world: &World) -> Vec<String> {
+        let mut nouns: Vec<String> = world
+            .iter()
+            .filter(|(pos, object)| {
+                !object.hidden
+                    && world.get_distance(Some(world.player), Some(*pos)) <= Distance::OverThere
+            })
+            .flat_map(|(_, object)| object.labels.iter().map(|label| label.to_lowercase()))
+            .collect();
+        nouns.sort();
+        nouns.dedup();
+        nouns
+    }
+
+    #[test]
+    fn index_matches_one_built_from_scratch() {
+        let mut world = game();
+        for input_str in [
+            "get photo",
+            "go aft",
+            "search table",
+            "robot, give note",
+            "drop photo",
+            "robot, go forward",
+            "get photo",
+            "go aft",
+            "talk to copilot",
+            "1",
+            "1",
+            "tell copilot about alarm",
+            "drop photo",
+            "get cryosuit",
+            "go forward",
+            "drop cryosuit",
+            "go forward",
+        ] {
+            let outcome = world.update_state(&parse(input_str.to_string()));
+            assert!(outcome.is_success(), "{}", input_str);
+            assert_eq!(
+                world.index,
+                WorldIndex::new(&world.objects),
+                "{}",
+                input_str
+            );
+            assert_eq!(
+                world.nouns_in_scope(),
+                scanned_nouns(&world),
+                "{}",
+                input_str
+            );
+        }
+    }
+}
@@ -0,0 +1,917 @@
+//
+// Reentry Lint
+//
+// Checks a game file for problems that loading it into a World does
+// not catch, but that make the game misbehave or panic while playing.
+use super::behaviour::SavedAction;
+use super::dialogue::SavedEffect;
+use super::template;
+use super::{Kind, ObjectId, SavedObject, SavedWorld};
+use serde::Serialize;
+use std::fmt;
+
+#[derive(Serialize, PartialOrd, Ord, PartialEq, Eq, Clone, Copy, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: &'static str,
+    pub object: Option<usize>,
+    pub id: String,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.object {
+            Some(index) => write!(
+                f,
+                "{}[{}]: '{}' (object {}): {}",
+                self.severity, self.code, self.id, index, self.message
+            ),
+            None => write!(f, "{}[{}]: {}", self.severity, self.code, self.message),
+        }
+    }
+}
+
+struct Linter<'a> {
+    world: &'a SavedWorld,
+    // The passages back that two way passages make when the game loads
+    reverses: Vec<SavedObject>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+// Where each piece of an object's dialogue is, for telling the author
+fn topic_field(pos: usize, field: &str) -> String {
+    format!("topic {} {}", pos + 1, field)
+}
+
+fn node_field(node: &str, field: &str) -> String {
+    format!("dialogue node '{}' {}", node, field)
+}
+
+fn behaviour_field(pos: usize, field: &str) -> String {
+    format!("behaviour {} {}", pos + 1, field)
+}
+
+// Game text in an object's topics and dialogue, by where it is
+fn dialogue_texts(item: &SavedObject) -> Vec<(String, &str)> {
+    let mut texts: Vec<(String, &str)> = Vec::new();
+    for (pos, topic) in item.topics.iter().enumerate() {
+        texts.push((topic_field(pos, "ask"), &topic.ask));
+        texts.push((topic_field(pos, "tell"), &topic.tell));
+    }
+    for node in &item.dialogue {
+        texts.push((node_field(&node.id, "text"), &node.text));
+        for choice in &node.choices {
+            texts.push((node_field(&node.id, "choice"), &choice.text));
+        }
+    }
+    texts
+}
+
+fn dialogue_conditions(item: &SavedObject) -> Vec<(String, &str)> {
+    let mut conditions: Vec<(String, &str)> = Vec::new();
+    for (pos, topic) in item.topics.iter().enumerate() {
+        conditions.push((topic_field(pos, "when"), &topic.when));
+    }
+    for node in &item.dialogue {
+        conditions.push((node_field(&node.id, "when"), &node.when));
+        for choice in &node.choices {
+            conditions.push((node_field(&node.id, "choice when"), &choice.when));
+        }
+    }
+    conditions
+}
+
+fn dialogue_effects(item: &SavedObject) -> Vec<&SavedEffect> {
+    item.topics
+        .iter()
+        .flat_map(|topic| &topic.effects)
+        .chain(item.dialogue.iter().flat_map(|node| &node.effects))
+        .collect()
+}
+
+impl Linter<'_> {
+    // Any object a name could refer to once the game has loaded
+    fn find(&self, name: &str) -> Option<&SavedObject> {
+        match self.world.find_object(name) {
+            Some(object) => Some(&self.world.objects[object.0]),
+            None => self.reverses.iter().find(|reverse| reverse.id == name),
+        }
+    }
+
+    // Objects missing an id are named by their first label instead
+    fn id(&self, index: usize) -> String {
+        let item = &self.world.objects[index];
+        match item.id.is_empty() {
+            true => item.labels.first().cloned().unwrap_or_default(),
+            false => item.id.clone(),
+        }
+    }
+
+    fn report(&mut self, severity: Severity, code: &'static str, index: usize, message: String) {
+        let id = self.id(index);
+        self.diagnostics.push(Diagnostic {
+            severity,
+            code,
+            object: Some(index),
+            id,
+            message,
+        });
+    }
+
+    fn check_labels(&mut self) {
+        for (pos, item) in self.world.objects.iter().enumerate() {
+            if item.labels.is_empty() {
+                self.report(
+                    Severity::Error,
+                    "empty-labels",
+                    pos,
+                    "object has no labels and cannot be named or described".into(),
+                );
+            }
+        }
+    }
+
+    fn check_ids(&mut self) {
+        for (pos, item) in self.world.objects.iter().enumerate() {
+            if item.id.is_empty() {
+                self.report(
+                    Severity::Error,
+                    "empty-id",
+                    pos,
+                    "object has no id and cannot be referred to".into(),
+                );
+                continue;
+            }
+
+            let first_use = self
+                .world
+                .objects
+                .iter()
+                .position(|other| other.id == item.id);
+            if first_use != Some(pos) {
+                self.report(
+                    Severity::Error,
+                    "duplicate-id",
+                    pos,
+                    format!(
+                        "id is also used by object {}, so references to it are ambiguous",
+                        first_use.unwrap_or_default()
+                    ),
+                );
+            }
+        }
+
+        // The passage back from a two way passage has an id of its own
+        for (pos, item) in self.world.objects.iter().enumerate() {
+            let Some(reverse_id) = item.reverse_id() else {
+                continue;
+            };
+            let other_use = self
+                .world
+                .objects
+                .iter()
+                .enumerate()
+                .position(|(other_pos, other)| {
+                    other.id == reverse_id
+                        || (other_pos != pos && other.reverse_id().as_ref() == Some(&reverse_id))
+                });
+            if let Some(other_use) = other_use {
+                self.report(
+                    Severity::Error,
+                    "duplicate-id",
+                    pos,
+                    format!(
+                        "reverse id '{}' is also used by object {}, so references to it are ambiguous",
+                        reverse_id, other_use
+                    ),
+                );
+            }
+        }
+    }
+
+    fn check_kinds(&mut self) {
+        for (pos, item) in self.world.objects.iter().enumerate() {
+            for problem in item.kind_problems() {
+                self.report(Severity::Error, "wrong-kind", pos, problem);
+            }
+        }
+    }
+
+    fn check_names(&mut self) {
+        for (pos, item) in self.world.objects.iter().enumerate() {
+            for (field, name) in [
+                ("location", &item.location),
+                ("destination", &item.destination),
+                ("prospect", &item.prospect),
+                ("revealed_by", &item.revealed_by),
+            ] {
+                if !name.is_empty() && self.world.find_object(name).is_none() {
+                    self.report(
+                        Severity::Error,
+                        "unknown-name",
+                        pos,
+                        format!("{} '{}' does not name any object", field, name),
+                    );
+                }
+            }
+
+            // Effects and actions have to name something, even if it is
+            // only the player
+            let named = dialogue_effects(item)
+                .into_iter()
+                .flat_map(SavedEffect::names)
+                .chain(
+                    item.behaviours
+                        .iter()
+                        .flat_map(|behaviour| behaviour.action.names()),
+                );
+            for (field, name) in named {
+                if self.find(name).is_none() {
+                    self.report(
+                        Severity::Error,
+                        "unknown-name",
+                        pos,
+                        format!("{} '{}' does not name any object", field, name),
+                    );
+                }
+            }
+        }
+    }
+
+    fn check_dialogue(&mut self) {
+        for (pos, item) in self.world.objects.iter().enumerate() {
+            for (node_pos, node) in item.dialogue.iter().enumerate() {
+                let first_use = item.dialogue.iter().position(|other| other.id == node.id);
+                if first_use != Some(node_pos) {
+                    self.report(
+                        Severity::Error,
+                        "duplicate-id",
+                        pos,
+                        format!("dialogue node id '{}' is used more than once", node.id),
+                    );
+                }
+                for choice in &node.choices {
+                    let known = choice.next.is_empty()
+                        || item.dialogue.iter().any(|other| other.id == choice.next);
+                    if !known {
+                        self.report(
+                            Severity::Error,
+                            "unknown-name",
+                            pos,
+                            format!(
+                                "dialogue node '{}' leads to '{}', which is not a node",
+                                node.id, choice.next
+                            ),
+                        );
+                    }
+                }
+            }
+
+            for (field, condition) in dialogue_conditions(item) {
+                self.check_condition(pos, &field, condition);
+            }
+        }
+    }
+
+    // Conditions are written as in a template's {if}
+    fn check_condition(&mut self, pos: usize, field: &str, condition: &str) {
+        match template::condition_names(condition) {
+            Ok(names) => {
+                for name in names {
+                    if self.world.find_object(&name).is_none() {
+                        self.report(
+                            Severity::Error,
+                            "unknown-name",
+                            pos,
+                            format!("{} refers to '{}', which is not an object", field, name),
+                        );
+                    }
+                }
+            }
+            Err(condition_err) => self.report(
+                Severity::Error,
+                "bad-condition",
+                pos,
+                format!("{} is not a valid condition: {}", field, condition_err),
+            ),
+        }
+    }
+
+    fn check_templates(&mut self) {
+        for (pos, item) in self.world.objects.iter().enumerate() {
+            let mut texts: Vec<(String, &str)> = vec![
+                ("description".into(), &item.description),
+                ("details".into(), &item.details),
+                ("contents".into(), &item.contents),
+                ("text_go".into(), &item.text_go),
+                ("refusal".into(), &item.refusal),
+                ("text_found".into(), &item.text_found),
+            ];
+            texts.extend(dialogue_texts(item));
+            for (behaviour_pos, behaviour) in item.behaviours.iter().enumerate() {
+                texts.push((behaviour_field(behaviour_pos, "text"), &behaviour.text));
+            }
+
+            for (field, text) in texts {
+                match template::object_names(text) {
+                    Ok(names) => {
+                        for name in names {
+                            // 'player' and 'here' always name something
+                            let known = template::is_special_name(&name)
+                                || self.world.find_object(&name).is_some();
+                            if !known {
+                                self.report(
+                                    Severity::Error,
+                                    "unknown-name",
+                                    pos,
+                                    format!(
+                                        "{} refers to '{}', which is not an object",
+                                        field, name
+                                    ),
+                                );
+                            }
+                        }
+                    }
+                    Err(template_err) => self.report(
+                        Severity::Error,
+                        "bad-template",
+                        pos,
+                        format!("{} is not a valid template: {}", field, template_err),
+                    ),
+                }
+            }
+        }
+    }
+
+    fn check_player(&mut self) {
+        let Some(player) = self.world.find_object(&self.world.player) else {
+            self.diagnostics.push(Diagnostic {
+                severity: Severity::Error,
+                code: "unknown-player",
+                object: None,
+                id: String::new(),
+                message: format!("player '{}' does not name any object", self.world.player),
+            });
+            return;
+        };
+
+        let kind = self.world.objects[player.0].kind;
+        if kind != Kind::Actor {
+            self.report(
+                Severity::Error,
+                "player-not-actor",
+                player.0,
+                format!(
+                    "this object is the player, but it is {}",
+                    kind.with_article()
+                ),
+            );
+        }
+    }
+
+    fn check_containment(&mut self) {
+        for pos in 0..self.world.objects.len() {
+            let mut chain: Vec<usize> = vec![pos];
+            let mut current = pos;
+
+            while let Some(ObjectId(next)) = self
+                .world
+                .find_object(&self.world.objects[current].location)
+            {
+                if next == pos {
+                    // Report each cycle once, against its lowest object
+                    if chain.iter().all(|&index| index >= pos) {
+                        let names: Vec<String> =
+                            chain.iter().map(|&index| self.id(index)).collect();
+                        self.report(
+                            Severity::Error,
+                            "containment-cycle",
+                            pos,
+                            format!(
+                                "objects contain each other: {} -> {}",
+                                names.join(" -> "),
+                                self.id(pos)
+                            ),
+                        );
+                    }
+                    break;
+                }
+                if chain.contains(&next) {
+                    // Part of a cycle that does not include this object
+                    break;
+                }
+                chain.push(next);
+                current = next;
+            }
+        }
+    }
+
+    fn check_passages(&mut self) {
+        for (pos, item) in self.world.objects.iter().enumerate() {
+            // A two way passage also leads back to where it starts
+            let location = match item.reverse {
+                Some(_) => item.location.as_str(),
+                None => "",
+            };
+            for (field, name) in [
+                ("destination", item.destination.as_str()),
+                ("prospect", item.prospect.as_str()),
+                ("location", location),
+            ] {
+                if let Some(target) = self.world.find_object(name) {
+                    if self.world.objects[target.0].kind != Kind::Location {
+                        self.report(
+                            Severity::Error,
+                            "destination-not-location",
+                            pos,
+                            format!("{} '{}' is not a location", field, name),
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    fn check_hidden(&mut self) {
+        for (pos, item) in self.world.objects.iter().enumerate() {
+            if item.hidden && item.revealed_by.is_empty() {
+                self.report(
+                    Severity::Warning,
+                    "never-revealed",
+                    pos,
+                    "is hidden, but nothing reveals it".to_string(),
+                );
+            } else if !item.hidden && !item.revealed_by.is_empty() {
+                self.report(
+                    Severity::Warning,
+                    "never-hidden",
+                    pos,
+                    format!(
+                        "is revealed by '{}', but is not hidden to begin with",
+                        item.revealed_by
+                    ),
+                );
+            }
+        }
+    }
+
+    fn check_behaviours(&mut self) {
+        for (pos, item) in self.world.objects.iter().enumerate() {
+            for (behaviour_pos, behaviour) in item.behaviours.iter().enumerate() {
+                match &behaviour.action {
+                    SavedAction::Patrol { route, .. } => self.check_route(pos, route),
+                    SavedAction::Take(name) | SavedAction::Drop(name) => {
+                        let problem = match self
+                            .find(name)
+                            .map(|object| (object.kind, object.fixed))
+                        {
+                            None | Some((Kind::Item, false)) => None,
+                            Some((Kind::Item, true)) => Some("it is fixed in place".to_string()),
+                            Some((kind, _)) => {
+                                Some(format!("that is {}, not an item", kind.with_article()))
+                            }
+                        };
+                        if let Some(problem) = problem {
+                            self.report(
+                                Severity::Warning,
+                                "cannot-carry",
+                                pos,
+                                format!("takes or drops '{}', but {}", name, problem),
+                            );
+                        }
+                    }
+                    SavedAction::Follow | SavedAction::Greet => (),
+                }
+                self.check_condition(
+                    pos,
+                    &behaviour_field(behaviour_pos, "when"),
+                    &behaviour.when,
+                );
+            }
+        }
+    }
+
+    // A route is a loop of passages, each starting where the one before
+    // it leads
+    fn check_route(&mut self, pos: usize, route: &[String]) {
+        let passages: Vec<SavedObject> = route
+            .iter()
+            .filter_map(|name| self.find(name).cloned())
+            .collect();
+
+        for passage in &passages {
+            if passage.kind != Kind::Passage {
+                self.report(
+                    Severity::Error,
+                    "wrong-kind",
+                    pos,
+                    format!(
+                        "patrols through '{}', but that is {}, not a passage",
+                        passage.id,
+                        passage.kind.with_article()
+                    ),
+                );
+            }
+        }
+        for (step, passage) in passages.iter().enumerate() {
+            let next = &passages[(step + 1) % passages.len()];
+            let both_passages = passage.kind == Kind::Passage && next.kind == Kind::Passage;
+            if both_passages && passage.destination != next.location {
+                self.report(
+                    Severity::Warning,
+                    "broken-route",
+                    pos,
+                    format!(
+                        "patrols through '{}' to '{}', but '{}' does not start there",
+                        passage.id, passage.destination, next.id
+                    ),
+                );
+            }
+        }
+    }
+
+    fn check_weights(&mut self) {
+        // Locations hold anything, so only items and actors, which can move,
+        // carry things around
+        let max_capacity = self
+            .world
+            .objects
+            .iter()
+            .filter(|item| matches!(item.kind, Kind::Item | Kind::Actor))
+            .map(|item| item.capacity)
+            .max()
+            .unwrap_or_default();
+
+        for (pos, item) in self.world.objects.iter().enumerate() {
+            if item.kind == Kind::Item && !item.fixed && item.weight > max_capacity {
+                self.report(
+                    Severity::Warning,
+                    "too-heavy",
+                    pos,
+                    format!(
+                        "weight {} is more than anything can carry (largest capacity is {})",
+                        item.weight, max_capacity
+                    ),
+                );
+            }
+        }
+    }
+}
+
+pub fn lint(world: &SavedWorld) -> Vec<Diagnostic> {
+    let (objects, reverse_of) = world.with_reverses();
+    let mut linter = Linter {
+        world,
+        reverses: objects
+            .into_iter()
+            .zip(reverse_of)
+            .filter(|(_, reverse_of)| reverse_of.is_some())
+            .map(|(reverse, _)| reverse)
+            .collect(),
+        diagnostics: Vec::new(),
+    };
+
+    linter.check_labels();
+    linter.check_ids();
+    linter.check_kinds();
+    linter.check_names();
+    linter.check_templates();
+    linter.check_player();
+    linter.check_containment();
+    linter.check_passages();
+    linter.check_hidden();
+    linter.check_dialogue();
+    linter.check_behaviours();
+    linter.check_weights();
+
+    linter.diagnostics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lint_objects(objects: &str) -> Vec<(Severity, &'static str, Option<usize>)> {
+        let world: SavedWorld = ron::from_str(&format!("(objects: [{}])", objects)).unwrap();
+        lint(&world)
+            .iter()
+            .map(|diagnostic| (diagnostic.severity, diagnostic.code, diagnostic.object))
+            .collect()
+    }
+
+    const PLAYER: &str = r#"(id: "player", kind: Actor, labels: ["Yourself"], description: "you",
+        location: "bridge", capacity: 20),"#;
+    const BRIDGE: &str =
+        r#"(id: "bridge", kind: Location, labels: ["Bridge"], description: "the bridge"),"#;
+
+    #[test]
+    fn a_sound_world_has_no_problems() {
+        let objects = format!(
+            r#"{}{}(id: "galley", kind: Location, labels: ["Galley"], description: "the galley"),
+               (id: "aft", kind: Passage, labels: ["Aft"], description: "a passage",
+                location: "bridge", destination: "galley", prospect: "galley"),
+               (id: "pen", kind: Item, labels: ["Pen"], description: "a pen",
+                location: "player", weight: 1)"#,
+            PLAYER, BRIDGE
+        );
+        assert_eq!(lint_objects(&objects), Vec::new());
+    }
+
+    #[test]
+    fn ids_must_be_given_once() {
+        let objects = format!(
+            r#"{}{}(id: "pen", kind: Item, labels: ["Pen"], description: "a pen",
+                location: "bridge", weight: 1),
+               (id: "pen", kind: Item, labels: ["Pen"], description: "a pen",
+                location: "bridge", weight: 1),
+               (id: "", kind: Item, labels: ["Pencil"], description: "a pencil",
+                location: "bridge", weight: 1)"#,
+            PLAYER, BRIDGE
+        );
+        assert_eq!(
+            lint_objects(&objects),
+            vec![
+                (Severity::Error, "duplicate-id", Some(3)),
+                (Severity::Error, "empty-id", Some(4)),
+            ]
+        );
+    }
+
+    #[test]
+    fn names_must_refer_to_objects() {
+        let objects = format!(
+            r#"{}{}(id: "pen", kind: Item, labels: ["Pen"], description: "a pen",
+                location: "galley", weight: 1),
+               (id: "aft", kind: Passage, labels: ["Aft"], description: "a passage",
+                location: "bridge", destination: "galley", prospect: "galley")"#,
+            PLAYER, BRIDGE
+        );
+        assert_eq!(
+            lint_objects(&objects),
+            vec![
+                (Severity::Error, "unknown-name", Some(2)),
+                (Severity::Error, "unknown-name", Some(3)),
+                (Severity::Error, "unknown-name", Some(3)),
+            ]
+        );
+    }
+
+    #[test]
+    fn the_player_must_be_an_actor() {
+        assert_eq!(
+            lint_objects(""),
+            vec![(Severity::Error, "unknown-player", None)]
+        );
+        let objects = format!(
+            r#"(id: "player", kind: Item, labels: ["Yourself"], description: "you",
+                location: "bridge", weight: 1, capacity: 1),{}"#,
+            BRIDGE
+        );
+        assert_eq!(
+            lint_objects(&objects),
+            vec![(Severity::Error, "player-not-actor", Some(0))]
+        );
+    }
+
+    #[test]
+    fn fields_must_suit_the_kind() {
+        let objects = format!(
+            r#"{}{}(id: "pen", kind: Item, labels: ["Pen"], description: "a pen",
+                location: "bridge", destination: "bridge", weight: 1)"#,
+            PLAYER, BRIDGE
+        );
+        assert_eq!(
+            lint_objects(&objects),
+            vec![(Severity::Error, "wrong-kind", Some(2))]
+        );
+    }
+
+    #[test]
+    fn containment_cycles_are_reported_once() {
+        let objects = format!(
+            r#"{}{}(id: "box", kind: Item, labels: ["Box"], description: "a box",
+                location: "crate", weight: 1),
+               (id: "crate", kind: Item, labels: ["Crate"], description: "a crate",
+                location: "box", weight: 1)"#,
+            PLAYER, BRIDGE
+        );
+        assert_eq!(
+            lint_objects(&objects),
+            vec![(Severity::Error, "containment-cycle", Some(2))]
+        );
+    }
+
+    #[test]
+    fn passages_lead_to_locations() {
+        let objects = format!(
+            r#"{}{}(id: "pen", kind: Item, labels: ["Pen"], description: "a pen",
+                location: "bridge", weight: 1),
+               (id: "aft", kind: Passage, labels: ["Aft"], description: "a passage",
+                location: "bridge", destination: "pen", prospect: "bridge")"#,
+            PLAYER, BRIDGE
+        );
+        assert_eq!(
+            lint_objects(&objects),
+            vec![(Severity::Error, "destination-not-location", Some(3))]
+        );
+    }
+
+    #[test]
+    fn things_too_heavy_for_anyone_to_carry() {
+        // Leaving out the weight is no different from giving it
+        let objects = format!(
+            r#"{}{}(id: "pen", kind: Item, labels: ["Pen"], description: "a pen",
+                location: "bridge", weight: 20),
+               (id: "anvil", kind: Item, labels: ["Anvil"], description: "an anvil",
+                location: "bridge", weight: 21),
+               (id: "statue", kind: Item, labels: ["Statue"], description: "a statue",
+                location: "bridge"),
+               (id: "bench", kind: Item, labels: ["Bench"], description: "a bench",
+                location: "bridge", fixed: true)"#,
+            PLAYER, BRIDGE
+        );
+        assert_eq!(
+            lint_objects(&objects),
+            vec![
+                (Severity::Warning, "too-heavy", Some(3)),
+                (Severity::Warning, "too-heavy", Some(4)),
+            ]
+        );
+    }
+
+    #[test]
+    fn refusals_are_templates() {
+        let objects = format!(
+            r#"{}{}(id: "panel", kind: Scenery, labels: ["Panel"], description: "a panel",
+                location: "bridge", refusal: "{{lever.name}} holds it shut."),
+               (id: "hatch", kind: Scenery, labels: ["Hatch"], description: "a hatch",
+                location: "bridge", refusal: "{{if here.name}}Stuck."),
+               (id: "grate", kind: Scenery, labels: ["Grate"], description: "a grate",
+                location: "bridge", refusal: "{{here.name}} keeps it.")"#,
+            PLAYER, BRIDGE
+        );
+        assert_eq!(
+            lint_objects(&objects),
+            vec![
+                (Severity::Error, "unknown-name", Some(2)),
+                (Severity::Error, "bad-template", Some(3)),
+            ]
+        );
+    }
+
+    #[test]
+    fn passages_back_need_ids_of_their_own() {
+        let objects = format!(
+            r#"{}{}(id: "galley", kind: Location, labels: ["Galley"], description: "the galley"),
+               (id: "aft", kind: Passage, labels: ["Aft"], description: "a passage",
+                location: "bridge", destination: "galley", reverse: Some(())),
+               (id: "aft_reverse", kind: Item, labels: ["Pen"], description: "a pen",
+                location: "bridge", weight: 1),
+               (id: "up", kind: Passage, labels: ["Up"], description: "a ladder",
+                location: "bridge", destination: "galley", reverse: Some((id: "ladder"))),
+               (id: "hatch", kind: Passage, labels: ["Hatch"], description: "a hatch",
+                location: "bridge", destination: "galley", reverse: Some((id: "ladder")))"#,
+            PLAYER, BRIDGE
+        );
+        assert_eq!(
+            lint_objects(&objects),
+            vec![
+                (Severity::Error, "duplicate-id", Some(3)),
+                (Severity::Error, "duplicate-id", Some(5)),
+                (Severity::Error, "duplicate-id", Some(6)),
+            ]
+        );
+    }
+
+    #[test]
+    fn passages_back_start_from_locations() {
+        let objects = format!(
+            r#"{}{}(id: "galley", kind: Location, labels: ["Galley"], description: "the galley"),
+               (id: "locker", kind: Item, labels: ["Locker"], description: "a locker",
+                location: "bridge", capacity: 5, weight: 1),
+               (id: "door", kind: Passage, labels: ["Door"], description: "a door",
+                location: "locker", destination: "galley", reverse: Some(()))"#,
+            PLAYER, BRIDGE
+        );
+        assert_eq!(
+            lint_objects(&objects),
+            vec![(Severity::Error, "destination-not-location", Some(4))]
+        );
+    }
+
+    #[test]
+    fn hidden_things_must_be_found_by_something() {
+        let objects = format!(
+            r#"{}{}(id: "desk", kind: Scenery, labels: ["Desk"], description: "a desk",
+                location: "bridge"),
+               (id: "note", kind: Item, labels: ["Note"], description: "a note",
+                location: "bridge", weight: 1, hidden: true, revealed_by: "desk",
+                text_found: "Under {{desk.name}} is a note."),
+               (id: "coin", kind: Item, labels: ["Coin"], description: "a coin",
+                location: "bridge", weight: 1, hidden: true),
+               (id: "key", kind: Item, labels: ["Key"], description: "a key",
+                location: "bridge", weight: 1, revealed_by: "desk"),
+               (id: "map", kind: Item, labels: ["Map"], description: "a map",
+                location: "bridge", weight: 1, hidden: true, revealed_by: "drawer",
+                text_found: "{{if}}")"#,
+            PLAYER, BRIDGE
+        );
+        assert_eq!(
+            lint_objects(&objects),
+            vec![
+                (Severity::Error, "unknown-name", Some(6)),
+                (Severity::Error, "bad-template", Some(6)),
+                (Severity::Warning, "never-revealed", Some(4)),
+                (Severity::Warning, "never-hidden", Some(5)),
+            ]
+        );
+    }
+
+    #[test]
+    fn dialogue_must_hang_together() {
+        let objects = format!(
+            r#"{}{}(id: "steward", kind: Actor, labels: ["Steward"], description: "a steward",
+                location: "bridge",
+                topics: [(about: ["Ship"], ask: "{{ghost.name}} knows.", when: "not bogus.flag",
+                          effects: [Move("nothing", "player")])],
+                dialogue: [
+                    (id: "hello", text: "Hello.",
+                     choices: [(text: "Go on.", next: "more"), (text: "Bye.", when: "turn.")]),
+                    (id: "hello", text: "Hello again."),
+                ])"#,
+            PLAYER, BRIDGE
+        );
+        let world: SavedWorld = ron::from_str(&format!("(objects: [{}])", objects)).unwrap();
+        let messages: Vec<String> = lint(&world)
+            .iter()
+            .map(|diagnostic| diagnostic.message.to_string())
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                "effect object 'nothing' does not name any object",
+                "topic 1 ask refers to 'ghost', which is not an object",
+                "dialogue node 'hello' leads to 'more', which is not a node",
+                "dialogue node id 'hello' is used more than once",
+                "topic 1 when refers to 'bogus', which is not an object",
+                "dialogue node 'hello' choice when is not a valid condition: \
+                 'turn.' is not 'turn' or OBJECT.FIELD",
+            ]
+        );
+    }
+
+    #[test]
+    fn behaviours_must_make_sense() {
+        let objects = format!(
+            r#"{}{}(id: "galley", kind: Location, labels: ["Galley"], description: "the galley"),
+               (id: "aft", kind: Passage, labels: ["Aft"], description: "a passage",
+                location: "bridge", destination: "galley", prospect: "galley"),
+               (id: "fore", kind: Passage, labels: ["Fore"], description: "a passage",
+                location: "bridge", destination: "galley", prospect: "galley"),
+               (id: "bench", kind: Item, labels: ["Bench"], description: "a bench",
+                location: "bridge", weight: 1, fixed: true),
+               (id: "robot", kind: Actor, labels: ["Robot"], description: "a robot",
+                location: "bridge",
+                behaviours: [
+                    (action: Patrol(route: ["aft", "fore", "bench"]), when: "ghost.flag"),
+                    (action: Take("bench"), text: "{{ghost.name}} heaves."),
+                    (action: Drop("galley"), when: "turn >"),
+                ])"#,
+            PLAYER, BRIDGE
+        );
+        let world: SavedWorld = ron::from_str(&format!("(objects: [{}])", objects)).unwrap();
+        let messages: Vec<String> = lint(&world)
+            .iter()
+            .map(|diagnostic| diagnostic.message.to_string())
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                "behaviour 2 text refers to 'ghost', which is not an object",
+                "patrols through 'bench', but that is an item, not a passage",
+                "patrols through 'aft' to 'galley', but 'fore' does not start there",
+                "behaviour 1 when refers to 'ghost', which is not an object",
+                "takes or drops 'bench', but it is fixed in place",
+                "takes or drops 'galley', but that is a location, not an item",
+                "behaviour 3 when is not a valid condition: 'turn >' is not 'turn' or OBJECT.FIELD",
+            ]
+        );
+    }
+}
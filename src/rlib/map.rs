@@ -0,0 +1,177 @@
+//
+// Reentry Map
+//
+// Draws the locations of a World and the passages between them as a graph
+// so that the layout of a game can be reviewed without playing it.
+use super::{Kind, ObjectId, World};
+
+#[derive(Clone, Copy, Debug)]
+pub enum MapFormat {
+    Dot,
+    Mermaid,
+}
+
+#[derive(Debug)]
+pub struct MapOptions {
+    pub format: MapFormat,
+    pub containment: bool,
+}
+
+enum EdgeStyle {
+    OneWay,
+    TwoWay,
+    Prospect,
+    Contains,
+}
+
+struct Edge {
+    from: usize,
+    to: usize,
+    label: String,
+    style: EdgeStyle,
+}
+
+fn passage_label(world: &World, passage: usize) -> String {
+    world.objects[passage].labels.join("/")
+}
+
+fn collect_edges(world: &World, containment: bool) -> Vec<Edge> {
+    let mut edges: Vec<Edge> = Vec::new();
+    let mut paired: Vec<bool> = vec![false; world.len()];
+
+    for (pos, object) in world.objects.iter().enumerate() {
+        let (Some(from), Some(to)) = (object.location, object.destination) else {
+            continue;
+        };
+
+        if !paired[pos] {
+            // A passage straight back makes this a two way connection,
+            // whether the game file declared it two way or not
+            let reverse = world
+                .iter()
+                .position(|(_, other)| other.reverse_of == Some(ObjectId(pos)))
+                .or_else(|| {
+                    world.iter().position(|(other_pos, other)| {
+                        other_pos.0 != pos
+                            && !paired[other_pos.0]
+                            && other.location == Some(to)
+                            && other.destination == Some(from)
+                    })
+                });
+
+            match reverse {
+                Some(reverse_pos) if from != to => {
+                    paired[reverse_pos] = true;
+                    edges.push(Edge {
+                        from: from.0,
+                        to: to.0,
+                        label: format!(
+                            "{} / {}",
+                            passage_label(world, pos),
+                            passage_label(world, reverse_pos)
+                        ),
+                        style: EdgeStyle::TwoWay,
+                    });
+                }
+                _ => edges.push(Edge {
+                    from: from.0,
+                    to: to.0,
+                    label: passage_label(world, pos),
+                    style: EdgeStyle::OneWay,
+                }),
+            }
+        }
+
+        if let Some(prospect) = object.prospect.filter(|prospect| *prospect != to) {
+            edges.push(Edge {
+                from: from.0,
+                to: prospect.0,
+                label: passage_label(world, pos),
+                style: EdgeStyle::Prospect,
+            });
+        }
+    }
+
+    if containment {
+        for (pos, object) in world.objects.iter().enumerate() {
+            if let (Some(location), None) = (object.location, object.destination) {
+                edges.push(Edge {
+                    from: location.0,
+                    to: pos,
+                    label: String::new(),
+                    style: EdgeStyle::Contains,
+                });
+            }
+        }
+    }
+
+    edges
+}
+
+fn quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn to_dot(world: &World, options: &MapOptions) -> String {
+    let mut output = String::from("digraph map {\n");
+
+    for (pos, object) in world.objects.iter().enumerate() {
+        let label = quote(&object.labels.join("/"));
+        if object.kind == Kind::Location {
+            output += &format!("\tn{} [label={}, shape=box]\n", pos, label);
+        } else if options.containment && object.kind != Kind::Passage {
+            output += &format!("\tn{} [label={}, shape=ellipse]\n", pos, label);
+        }
+    }
+
+    for edge in collect_edges(world, options.containment) {
+        let style = match edge.style {
+            EdgeStyle::OneWay => "",
+            EdgeStyle::TwoWay => ", dir=both",
+            EdgeStyle::Prospect => ", style=dashed",
+            EdgeStyle::Contains => ", style=dotted, arrowhead=none",
+        };
+        output += &format!(
+            "\tn{} -> n{} [label={}{}]\n",
+            edge.from,
+            edge.to,
+            quote(&edge.label),
+            style
+        );
+    }
+
+    output + "}\n"
+}
+
+fn to_mermaid(world: &World, options: &MapOptions) -> String {
+    let mut output = String::from("flowchart LR\n");
+
+    for (pos, object) in world.objects.iter().enumerate() {
+        let label = object.labels.join("/").replace('"', "#quot;");
+        if object.kind == Kind::Location {
+            output += &format!("    n{}[\"{}\"]\n", pos, label);
+        } else if options.containment && object.kind != Kind::Passage {
+            output += &format!("    n{}([\"{}\"])\n", pos, label);
+        }
+    }
+
+    for edge in collect_edges(world, options.containment) {
+        let label = edge.label.replace('"', "#quot;");
+        let arrow = match edge.style {
+            EdgeStyle::OneWay => format!("-->|\"{}\"|", label),
+            EdgeStyle::TwoWay => format!("<-->|\"{}\"|", label),
+            EdgeStyle::Prospect => format!("-.->|\"{}\"|", label),
+            EdgeStyle::Contains => "---".to_string(),
+        };
+        output += &format!("    n{} {} n{}\n", edge.from, arrow, edge.to);
+    }
+
+    output
+}
+
+pub fn export(world: &World, options: &MapOptions) -> String {
+    match options.format {
+        MapFormat::Dot => to_dot(world, options),
+        MapFormat::Mermaid => to_mermaid(world, options),
+    }
+}
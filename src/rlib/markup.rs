@@ -0,0 +1,325 @@
+//
+// Reentry Markup
+//
+// Game text can be marked up with a few tags:
+//
+//      [b]bold[/b]  [i]italic[/i]  [color=red]coloured[/color]
+//      [h]a heading[/h]  [br] for a line break  [nbsp] for a space that
+//      never breaks  [[ for a literal '['
+//
+// Colours are black, red, green, yellow, blue, magenta, cyan and white.
+// A closing tag ends the most recent opening tag when it has the same name.
+// Closing tags that match nothing, and tags that are not understood, are
+// left in the text as they are.
+use std::fmt;
+
+pub const NBSP: char = '\u{a0}';
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Color {
+    Black,
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+    White,
+}
+
+impl Color {
+    fn from_name(name: &str) -> Option<Color> {
+        match name {
+            "black" => Some(Color::Black),
+            "red" => Some(Color::Red),
+            "green" => Some(Color::Green),
+            "yellow" => Some(Color::Yellow),
+            "blue" => Some(Color::Blue),
+            "magenta" => Some(Color::Magenta),
+            "cyan" => Some(Color::Cyan),
+            "white" => Some(Color::White),
+            _ => None,
+        }
+    }
+
+    fn ansi_code(&self) -> u8 {
+        match self {
+            Color::Black => 30,
+            Color::Red => 31,
+            Color::Green => 32,
+            Color::Yellow => 33,
+            Color::Blue => 34,
+            Color::Magenta => 35,
+            Color::Cyan => 36,
+            Color::White => 37,
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Default, Debug)]
+pub struct Style {
+    pub bold: bool,
+    pub italic: bool,
+    pub underline: bool,
+    pub color: Option<Color>,
+}
+
+impl Style {
+    pub fn is_plain(&self) -> bool {
+        *self == Style::default()
+    }
+
+    pub fn ansi_start(&self) -> String {
+        let mut codes: Vec<String> = Vec::new();
+        if self.bold {
+            codes.push("1".into());
+        }
+        if self.italic {
+            codes.push("3".into());
+        }
+        if self.underline {
+            codes.push("4".into());
+        }
+        if let Some(color) = self.color {
+            codes.push(color.ansi_code().to_string());
+        }
+        format!("\x1b[{}m", codes.join(";"))
+    }
+
+    pub fn ansi_end(&self) -> &'static str {
+        "\x1b[0m"
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Span {
+    pub text: String,
+    pub style: Style,
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.text.replace(NBSP, " "))
+    }
+}
+
+fn push_text(spans: &mut Vec<Span>, text: &str, style: Style) {
+    match spans.last_mut() {
+        Some(last) if last.style == style => last.text.push_str(text),
+        _ => spans.push(Span {
+            text: text.to_string(),
+            style,
+        }),
+    }
+}
+
+pub fn parse(text: &str) -> Vec<Span> {
+    let mut spans: Vec<Span> = Vec::new();
+    // Each open tag, by name, with the style it started
+    let mut styles: Vec<(&str, Style)> = vec![("", Style::default())];
+    let mut rest = text;
+
+    while let Some(open) = rest.find('[') {
+        let style = styles.last().unwrap().1;
+        push_text(&mut spans, &rest[..open], style);
+        rest = &rest[open..];
+
+        if let Some(after) = rest.strip_prefix("[[") {
+            push_text(&mut spans, "[", style);
+            rest = after;
+            continue;
+        }
+
+        let Some(close) = rest.find(']') else {
+            break;
+        };
+        let tag = &rest[1..close];
+        let mut new_style = style;
+
+        // Every tag that opens pushes a style, even one that changes nothing,
+        // so that its closing tag has something to end
+        let opened = match tag {
+            "b" => {
+                new_style.bold = true;
+                Some(tag)
+            }
+            "i" => {
+                new_style.italic = true;
+                Some(tag)
+            }
+            "h" => {
+                new_style.bold = true;
+                new_style.underline = true;
+                Some(tag)
+            }
+            "br" => {
+                push_text(&mut spans, "\n", style);
+                None
+            }
+            "nbsp" => {
+                push_text(&mut spans, &NBSP.to_string(), style);
+                None
+            }
+            _ => match tag.strip_prefix("color=").and_then(Color::from_name) {
+                Some(color) => {
+                    new_style.color = Some(color);
+                    Some("color")
+                }
+                None => {
+                    let open_tag = styles.last().unwrap().0;
+                    if styles.len() > 1 && tag.strip_prefix('/') == Some(open_tag) {
+                        styles.pop();
+                    } else {
+                        push_text(&mut spans, &rest[..=close], style);
+                    }
+                    None
+                }
+            },
+        };
+
+        if let Some(name) = opened {
+            styles.push((name, new_style));
+        }
+        rest = &rest[close + 1..];
+    }
+
+    push_text(&mut spans, rest, styles.last().unwrap().1);
+    spans
+}
+
+// The text without any markup, as it would appear on a plain terminal
+pub fn strip(text: &str) -> String {
+    parse(text).iter().map(|span| span.to_string()).collect()
+}
+
+pub fn heading(text: &str) -> String {
+    format!("[h]{}[/h]", text.replace('[', "[["))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BOLD: Style = Style {
+        bold: true,
+        italic: false,
+        underline: false,
+        color: None,
+    };
+
+    fn colored(color: Color) -> Style {
+        Style {
+            color: Some(color),
+            ..Style::default()
+        }
+    }
+
+    // The spans with any text in them, as (text, style)
+    fn spans(text: &str) -> Vec<(String, Style)> {
+        parse(text)
+            .into_iter()
+            .filter(|span| !span.text.is_empty())
+            .map(|span| (span.text, span.style))
+            .collect()
+    }
+
+    fn plain(text: &str) -> (String, Style) {
+        (text.to_string(), Style::default())
+    }
+
+    #[test]
+    fn plain_text_is_one_span() {
+        assert_eq!(spans("no markup here"), vec![plain("no markup here")]);
+    }
+
+    #[test]
+    fn tags_style_their_text() {
+        assert_eq!(
+            spans("a [b]bold[/b] and [color=red]red[/color] word"),
+            vec![
+                plain("a "),
+                ("bold".to_string(), BOLD),
+                plain(" and "),
+                ("red".to_string(), colored(Color::Red)),
+                plain(" word"),
+            ]
+        );
+    }
+
+    #[test]
+    fn styles_nest() {
+        let bold_italic = Style {
+            italic: true,
+            ..BOLD
+        };
+        assert_eq!(
+            spans("[b]b [i]bi[/i] b[/b]"),
+            vec![
+                ("b ".to_string(), BOLD),
+                ("bi".to_string(), bold_italic),
+                (" b".to_string(), BOLD),
+            ]
+        );
+    }
+
+    #[test]
+    fn headings_are_bold_and_underlined() {
+        let title = parse(&heading("Bridge [aft]"))
+            .into_iter()
+            .find(|span| !span.text.is_empty())
+            .unwrap();
+        assert_eq!(title.text, "Bridge [aft]");
+        assert!(title.style.bold && title.style.underline);
+    }
+
+    #[test]
+    fn unknown_colour_leaves_outer_style_alone() {
+        assert_eq!(
+            spans("[b]bold [color=purple]x[/color] still bold[/b] plain"),
+            vec![
+                ("bold [color=purple]x[/color] still bold".to_string(), BOLD),
+                plain(" plain"),
+            ]
+        );
+    }
+
+    #[test]
+    fn same_colour_can_nest() {
+        assert_eq!(
+            spans("[color=red]r [color=red]rr[/color] still red[/color] plain"),
+            vec![
+                ("r rr still red".to_string(), colored(Color::Red)),
+                plain(" plain"),
+            ]
+        );
+    }
+
+    #[test]
+    fn unmatched_closing_tags_are_text() {
+        assert_eq!(spans("a [/b] b"), vec![plain("a [/b] b")]);
+        assert_eq!(
+            spans("[i]x[/b]y[/i]"),
+            vec![(
+                "x[/b]y".to_string(),
+                Style {
+                    italic: true,
+                    ..Style::default()
+                }
+            )]
+        );
+    }
+
+    #[test]
+    fn unknown_tags_are_text() {
+        assert_eq!(spans("[u]x[/u]"), vec![plain("[u]x[/u]")]);
+        assert_eq!(spans("an open [bracket"), vec![plain("an open [bracket")]);
+    }
+
+    #[test]
+    fn escapes_breaks_and_spaces() {
+        assert_eq!(strip("[[b] is bold"), "[b] is bold");
+        assert_eq!(strip("one[br]two"), "one\ntwo");
+        assert_eq!(spans("a[nbsp]b"), vec![plain("a\u{a0}b")]);
+        assert_eq!(strip("a[nbsp]b"), "a b");
+    }
+}
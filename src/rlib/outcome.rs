@@ -0,0 +1,270 @@
+//
+// Reentry Outcomes
+//
+// What happened when a command was carried out. An Outcome says whether
+// the command worked, what the game has to say about it, and what changed
+// in the World, so callers can react to more than just the text.
+use super::markup;
+use super::ObjectId;
+use std::fmt;
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum FailureKind {
+    UnknownCommand,
+    UnknownObject,
+    NotHere,
+    Ambiguous,
+    TooFar,
+    NotHeld,
+    AlreadyDone,
+    NotAllowed,
+    TooHeavy,
+    NoActor,
+    Blocked,
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum OutcomeKind {
+    Success,
+    Failure(FailureKind),
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum MessageCategory {
+    RoomTitle,
+    Description,
+    ObjectList,
+    Narration,
+    Error,
+    System,
+}
+
+#[derive(Clone, Debug)]
+pub struct Message {
+    pub category: MessageCategory,
+    pub text: String,
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Change {
+    ObjectMoved {
+        object: ObjectId,
+        from: Option<ObjectId>,
+        to: ObjectId,
+    },
+    PlayerMoved {
+        from: Option<ObjectId>,
+        to: ObjectId,
+    },
+    ObjectRevealed {
+        object: ObjectId,
+    },
+    PropertyChanged {
+        object: ObjectId,
+    },
+}
+
+#[derive(Clone, Debug)]
+pub struct Outcome {
+    pub kind: OutcomeKind,
+    pub messages: Vec<Message>,
+    pub changes: Vec<Change>,
+    pub turn_consumed: bool,
+}
+
+impl Outcome {
+    pub fn success() -> Outcome {
+        Outcome {
+            kind: OutcomeKind::Success,
+            messages: Vec::new(),
+            changes: Vec::new(),
+            turn_consumed: true,
+        }
+    }
+
+    // Failed commands do not use up a turn
+    pub fn failure(kind: FailureKind, text: impl Into<String>) -> Outcome {
+        Outcome {
+            kind: OutcomeKind::Failure(kind),
+            messages: Vec::new(),
+            changes: Vec::new(),
+            turn_consumed: false,
+        }
+        .with(MessageCategory::Error, text)
+    }
+
+    pub fn with(mut self, category: MessageCategory, text: impl Into<String>) -> Outcome {
+        self.messages.push(Message {
+            category,
+            text: text.into(),
+        });
+        self
+    }
+
+    pub fn with_change(mut self, change: Change) -> Outcome {
+        self.changes.push(change);
+        self
+    }
+
+    pub fn without_turn(mut self) -> Outcome {
+        self.turn_consumed = false;
+        self
+    }
+
+    // Adds what happened next. A failure anywhere makes the whole outcome
+    // a failure.
+    pub fn then(mut self, next: Outcome) -> Outcome {
+        if self.kind == OutcomeKind::Success {
+            self.kind = next.kind;
+        }
+        self.messages.extend(next.messages);
+        self.changes.extend(next.changes);
+        self.turn_consumed = self.turn_consumed || next.turn_consumed;
+        self
+    }
+
+    pub fn is_success(&self) -> bool {
+        self.kind == OutcomeKind::Success
+    }
+
+    // The text with all markup removed, for comparing against transcripts
+    pub fn to_plain(&self) -> String {
+        markup::strip(&self.to_string())
+    }
+}
+
+// Still marked up, with room titles shown as headings
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for message in &self.messages {
+            match message.category {
+                MessageCategory::RoomTitle => writeln!(f, "{}", markup::heading(&message.text))?,
+                _ => writeln!(f, "{}", message.text)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rlib::{parse, SavedWorld, World};
+
+    fn world() -> World {
+        let saved: SavedWorld = ron::from_str(
+            r#"(objects: [
+                (id: "player", kind: Actor, labels: ["Yourself"], description: "you",
+                 location: "bridge", capacity: 10),
+                (id: "bridge", kind: Location, labels: ["Bridge"], description: "the bridge"),
+                (id: "galley", kind: Location, labels: ["Galley"], description: "the galley"),
+                (id: "bridge_aft", kind: Passage, labels: ["Aft"], description: "a passage",
+                 location: "bridge", destination: "galley"),
+                (id: "glossy_photo", kind: Item, labels: ["Glossy Photo", "Photo"],
+                 description: "a glossy photo", location: "bridge", weight: 1),
+                (id: "wrinkled_photo", kind: Item, labels: ["Wrinkled Photo", "Photo"],
+                 description: "a wrinkled photo", location: "bridge", weight: 1),
+                (id: "table", kind: Scenery, labels: ["Table"], description: "a table",
+                 location: "galley"),
+             ])"#,
+        )
+        .unwrap();
+        saved.try_into().unwrap()
+    }
+
+    fn categories(outcome: &Outcome) -> Vec<MessageCategory> {
+        outcome
+            .messages
+            .iter()
+            .map(|message| message.category)
+            .collect()
+    }
+
+    #[test]
+    fn looking_around_is_titled_and_described() {
+        let outcome = world().update_state(&parse("look".to_string()));
+        assert!(outcome.is_success());
+        assert_eq!(
+            categories(&outcome),
+            vec![
+                MessageCategory::RoomTitle,
+                MessageCategory::Description,
+                MessageCategory::ObjectList
+            ]
+        );
+        assert_eq!(outcome.messages[0].text, "Bridge");
+    }
+
+    #[test]
+    fn failures_are_errors_and_take_no_turn() {
+        let mut world = world();
+        for (input, kind) in [
+            ("get photo", FailureKind::Ambiguous),
+            ("get table", FailureKind::NotHere),
+            ("get yourself", FailureKind::NotAllowed),
+            ("dance", FailureKind::UnknownCommand),
+        ] {
+            let outcome = world.update_state(&parse(input.to_string()));
+            assert_eq!(outcome.kind, OutcomeKind::Failure(kind), "{}", input);
+            assert_eq!(categories(&outcome), vec![MessageCategory::Error]);
+            assert!(!outcome.turn_consumed, "{}", input);
+            assert!(outcome.changes.is_empty());
+        }
+    }
+
+    #[test]
+    fn successes_take_a_turn_and_record_changes() {
+        let mut world = world();
+        let outcome = world.update_state(&parse("get glossy photo".to_string()));
+        assert!(outcome.is_success());
+        assert!(outcome.turn_consumed);
+        assert_eq!(categories(&outcome), vec![MessageCategory::Narration]);
+        assert_eq!(
+            outcome.changes,
+            vec![Change::ObjectMoved {
+                object: world.find_id("glossy_photo").unwrap(),
+                from: world.find_id("bridge"),
+                to: world.player
+            }]
+        );
+
+        let outcome = world.update_state(&parse("go aft".to_string()));
+        assert!(outcome.turn_consumed);
+        assert_eq!(
+            outcome.changes,
+            vec![Change::PlayerMoved {
+                from: world.find_id("bridge"),
+                to: world.find_id("galley").unwrap()
+            }]
+        );
+    }
+
+    #[test]
+    fn inventory_and_quit_take_no_turn() {
+        let mut world = world();
+        let outcome = world.update_state(&parse("inventory".to_string()));
+        assert!(outcome.is_success());
+        assert!(!outcome.turn_consumed);
+        assert_eq!(categories(&outcome), vec![MessageCategory::ObjectList]);
+
+        let outcome = world.update_state(&parse("quit".to_string()));
+        assert!(!outcome.turn_consumed);
+        assert_eq!(categories(&outcome), vec![MessageCategory::System]);
+    }
+
+    #[test]
+    fn a_failure_later_fails_the_whole_outcome() {
+        let outcome = Outcome::success()
+            .with(MessageCategory::Narration, "You pick it up.")
+            .then(Outcome::failure(
+                FailureKind::TooHeavy,
+                "That is way too heavy.",
+            ));
+        assert_eq!(outcome.kind, OutcomeKind::Failure(FailureKind::TooHeavy));
+        assert!(outcome.turn_consumed);
+        assert_eq!(
+            outcome.to_string(),
+            "You pick it up.\nThat is way too heavy.\n"
+        );
+    }
+}
@@ -0,0 +1,138 @@
+//
+// Reentry Scripts
+//
+// A log of a game being played, written as it happens. Scripts are written
+// in the transcript format with the time of each command as a comment, so
+// a playtester's script can be replayed to reproduce what they saw, or run
+// with 'reentry test' to check the game still behaves that way.
+use super::transcript;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const DEF_SCRIPT_FILE: &str = "reentry-script.txt";
+
+// Converts days since 1970-01-01 into a (year, month, day) date
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month as u32, day as u32)
+}
+
+pub fn timestamp() -> String {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs() as i64);
+    let (year, month, day) = civil_from_days(seconds.div_euclid(86_400));
+    let time = seconds.rem_euclid(86_400);
+
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+        year,
+        month,
+        day,
+        time / 3600,
+        time % 3600 / 60,
+        time % 60
+    )
+}
+
+// Whether the input is one of the script commands, which are never logged
+pub fn is_script_command(input_str: &str) -> bool {
+    input_str
+        .split_whitespace()
+        .next()
+        .is_some_and(|verb| verb.eq_ignore_ascii_case("script"))
+}
+
+#[derive(Debug)]
+pub struct Script {
+    file: File,
+    path: PathBuf,
+}
+
+impl Script {
+    // Commands played before the script started are written without their
+    // output, so replaying the script reaches the same point in the game
+    pub fn create(path: impl AsRef<Path>, history: &[String]) -> io::Result<Script> {
+        let mut script = Script {
+            file: File::create(&path)?,
+            path: path.as_ref().to_path_buf(),
+        };
+
+        writeln!(script.file, "# Reentry script started {}", timestamp())?;
+        let earlier: Vec<&String> = history
+            .iter()
+            .filter(|input_str| !input_str.trim().is_empty() && !is_script_command(input_str))
+            .collect();
+        if !earlier.is_empty() {
+            writeln!(script.file, "# Commands played before the script started")?;
+            for input_str in earlier {
+                writeln!(script.file, "> {}", input_str.trim())?;
+            }
+        }
+        script.file.flush()?;
+        Ok(script)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn log(&mut self, input_str: &str, output: &str) -> io::Result<()> {
+        writeln!(self.file, "# {}", timestamp())?;
+        write!(self.file, "{}", transcript::record(input_str, output))?;
+        self.file.flush()
+    }
+
+    pub fn finish(mut self) -> io::Result<()> {
+        writeln!(self.file, "# Reentry script stopped {}", timestamp())?;
+        self.file.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn days_become_dates() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(31), (1970, 2, 1));
+        assert_eq!(civil_from_days(365), (1971, 1, 1));
+        assert_eq!(civil_from_days(-1), (1969, 12, 31));
+    }
+
+    #[test]
+    fn leap_days_are_counted() {
+        // 2000 is a leap year, 1900 and 2100 are not
+        assert_eq!(civil_from_days(11_016), (2000, 2, 29));
+        assert_eq!(civil_from_days(11_017), (2000, 3, 1));
+        assert_eq!(civil_from_days(-25_509), (1900, 2, 28));
+        assert_eq!(civil_from_days(-25_508), (1900, 3, 1));
+        assert_eq!(civil_from_days(47_540), (2100, 2, 28));
+        assert_eq!(civil_from_days(47_541), (2100, 3, 1));
+        assert_eq!(civil_from_days(19_782), (2024, 2, 29));
+    }
+
+    #[test]
+    fn script_commands_are_recognised() {
+        assert!(is_script_command("script on"));
+        assert!(is_script_command("  SCRIPT off"));
+        assert!(!is_script_command("scripts"));
+        assert!(!is_script_command("look script"));
+    }
+}
@@ -0,0 +1,301 @@
+//
+// Reentry Session
+//
+// A game in progress. The Session owns the World and everything a front
+// end would otherwise need to keep track of between turns, so that a front
+// end only has to pass along what the player types and show the response.
+use super::format::{format_text, terminal_width};
+use super::outcome::{FailureKind, OutcomeKind};
+use super::script::{self, Script, DEF_SCRIPT_FILE};
+use super::{parse, Command, Settings, World};
+use std::io;
+use std::path::Path;
+
+#[derive(Debug)]
+pub struct Response {
+    pub output: String,
+    // Why the command failed, if it did
+    pub failure: Option<FailureKind>,
+    pub game_over: bool,
+}
+
+// A command that named more than one object, waiting for the player to
+// say which one they meant
+#[derive(Debug)]
+struct Pending {
+    verb: String,
+    noun: String,
+    answers: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct Session {
+    pub world: World,
+    pub settings: Settings,
+    pub history: Vec<String>,
+    // What was played, with any answer to "which one do you mean?" turned
+    // back into the full command, so a script can be played back without
+    // the question being asked
+    played: Vec<String>,
+    pending: Option<Pending>,
+    script: Option<Script>,
+    over: bool,
+}
+
+impl Session {
+    pub fn new(world: World, settings: Settings) -> Session {
+        Session {
+            world,
+            settings,
+            history: Vec::new(),
+            played: Vec::new(),
+            pending: None,
+            script: None,
+            over: false,
+        }
+    }
+
+    fn width(&self) -> usize {
+        self.settings.width.unwrap_or_else(terminal_width)
+    }
+
+    pub fn intro(&self) -> String {
+        let intro = "Welcome to Reentry. A space adventure.\n\
+         \n\
+         You awake in darkness with a pounding headache.\n\
+         An alarm is flashing and beeping loudly. This doesn't help your headache.\n";
+
+        format_text(intro, self.width(), self.settings.color)
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.over
+    }
+
+    // Starts logging the game to a script, replacing any script already
+    // being written
+    pub fn start_script(&mut self, path: impl AsRef<Path>) -> io::Result<()> {
+        self.stop_script()?;
+        self.script = Some(Script::create(path, &self.played)?);
+        Ok(())
+    }
+
+    pub fn stop_script(&mut self) -> io::Result<()> {
+        match self.script.take() {
+            Some(script) => script.finish(),
+            None => Ok(()),
+        }
+    }
+
+    // 'script on [FILE]' and 'script off' control the script rather than
+    // the game, so they never take a turn
+    fn do_script(&mut self, input_str: &str) -> String {
+        let mut words = input_str.split_whitespace().skip(1);
+        let switch = words.next().unwrap_or_default().to_lowercase();
+        let file = words.collect::<Vec<&str>>().join(" ");
+
+        match (switch.as_str(), &self.script) {
+            ("on", _) => {
+                let file = if file.is_empty() {
+                    DEF_SCRIPT_FILE.to_string()
+                } else {
+                    file
+                };
+                match self.start_script(&file) {
+                    Ok(()) => format!("Writing a script of the game to '{}'.\n", file),
+                    Err(io_err) => format!("Unable to write a script to '{}': {}\n", file, io_err),
+                }
+            }
+            ("off", Some(script)) => {
+                let file = script.path().display().to_string();
+                match self.stop_script() {
+                    Ok(()) => format!("The script was saved to '{}'.\n", file),
+                    Err(io_err) => format!("Unable to finish the script '{}': {}\n", file, io_err),
+                }
+            }
+            ("off", None) => "No script is being written.\n".to_string(),
+            _ => "Use 'script on [FILE]' or 'script off'.\n".to_string(),
+        }
+    }
+
+    // Turns an answer to "which one do you mean?" back into a full command.
+    // Anything that does not name one of the objects meant is not an answer.
+    fn resolve_pending(&self, pending: &Pending, input_str: &str) -> Option<String> {
+        let answer = input_str.trim().to_lowercase();
+        let answer_noun = format!("{} {}", answer, pending.noun.to_lowercase());
+
+        if pending.answers.contains(&answer) {
+            Some(format!("{} {}", pending.verb, answer))
+        } else if pending.answers.contains(&answer_noun) {
+            Some(format!("{} {}", pending.verb, answer_noun))
+        } else {
+            None
+        }
+    }
+
+    pub fn step(&mut self, input_str: &str) -> Response {
+        if self.over {
+            return Response {
+                output: "The game is over.\n".to_string(),
+                failure: None,
+                game_over: true,
+            };
+        }
+
+        self.history.push(input_str.to_string());
+
+        if script::is_script_command(input_str) {
+            let output = self.do_script(input_str);
+            return Response {
+                output: format_text(&output, self.width(), self.settings.color),
+                failure: None,
+                game_over: false,
+            };
+        }
+
+        let mut input_str = input_str.to_string();
+        if let (Command::Unknown(_), Some(pending)) = (parse(input_str.to_string()), &self.pending)
+        {
+            if let Some(resolved) = self.resolve_pending(pending, &input_str) {
+                input_str = resolved;
+            }
+        }
+        let command = parse(input_str.to_string());
+        self.played.push(input_str.to_string());
+
+        let mut output = String::new();
+        if self.settings.debug {
+            output += &format!("[debug] {:?}\n", command);
+        }
+
+        let outcome = self.world.update_state(&command);
+        output += &outcome.to_string();
+        if let Some(script) = &mut self.script {
+            if let Err(io_err) = script.log(&input_str, &outcome.to_plain()) {
+                output += &format!(
+                    "Scripting stopped, unable to write the script: {}\n",
+                    io_err
+                );
+                self.script = None;
+            }
+        }
+        if self.settings.debug {
+            output += &format!("[debug] {:?} {:?}\n", outcome.kind, outcome.changes);
+        }
+
+        self.pending = match (outcome.kind, command.noun()) {
+            (OutcomeKind::Failure(FailureKind::Ambiguous), Some(noun)) => Some(Pending {
+                verb: command.to_string(),
+                noun: noun.to_string(),
+                answers: self.world.labels_meant_by(noun),
+            }),
+            _ => None,
+        };
+
+        if matches!(command, Command::Quit) {
+            self.over = true;
+        } else if self.world.is_won() {
+            output += "\nYou have done everything you set out to do.\n";
+            self.over = true;
+        }
+
+        if self.over {
+            let _ = self.stop_script();
+        }
+
+        Response {
+            output: format_text(&output, self.width(), self.settings.color),
+            failure: match outcome.kind {
+                OutcomeKind::Failure(kind) => Some(kind),
+                OutcomeKind::Success => None,
+            },
+            game_over: self.over,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rlib::transcript::Transcript;
+    use crate::rlib::SavedWorld;
+
+    fn session() -> Session {
+        let saved: SavedWorld = ron::from_str(
+            r#"(objects: [
+                (id: "player", kind: Actor, labels: ["Yourself"], description: "you",
+                 location: "bridge", capacity: 10),
+                (id: "bridge", kind: Location, labels: ["Bridge"], description: "the bridge"),
+                (id: "glossy_photo", kind: Item, labels: ["Glossy Photo", "Photo"],
+                 description: "a glossy photo", location: "bridge", weight: 1),
+                (id: "wrinkled_photo", kind: Item, labels: ["Wrinkled Photo", "Photo"],
+                 description: "a wrinkled photo", location: "bridge", weight: 1),
+             ])"#,
+        )
+        .unwrap();
+        Session::new(saved.try_into().unwrap(), Settings::default())
+    }
+
+    fn holding(session: &Session, id: &str) -> bool {
+        let world = &session.world;
+        world[world.find_id(id).unwrap()].location == Some(world.player)
+    }
+
+    #[test]
+    fn answers_finish_the_command_that_was_unclear() {
+        let mut session = session();
+        session.step("get photo");
+        assert!(!holding(&session, "glossy_photo"));
+
+        session.step("glossy");
+        assert!(holding(&session, "glossy_photo"));
+        assert!(!holding(&session, "wrinkled_photo"));
+    }
+
+    #[test]
+    fn anything_else_is_an_ordinary_command() {
+        let mut session = session();
+        session.step("get photo");
+
+        let mut fresh = self::session();
+        let response = session.step("xyzzy");
+        assert_eq!(response.output, fresh.step("xyzzy").output);
+        assert!(!holding(&session, "glossy_photo"));
+        assert!(!holding(&session, "wrinkled_photo"));
+
+        // The question has been dropped, so a late answer is not one
+        session.step("glossy");
+        assert!(!holding(&session, "glossy_photo"));
+    }
+
+    #[test]
+    fn scripts_hold_what_was_played() {
+        let mut session = session();
+        for input_str in [
+            "get photo",
+            "glossy",
+            "drop glossy photo",
+            "get photo",
+            "xyzzy",
+        ] {
+            session.step(input_str);
+        }
+
+        let path = std::env::temp_dir().join(format!("reentry-played-{}.txt", std::process::id()));
+        session.start_script(&path).unwrap();
+        session.stop_script().unwrap();
+        let text = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            Transcript::parse(&text).unwrap().inputs(),
+            vec![
+                "get photo",
+                "get glossy photo",
+                "drop glossy photo",
+                "get photo",
+                "xyzzy"
+            ]
+        );
+    }
+}
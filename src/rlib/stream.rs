@@ -0,0 +1,256 @@
+//
+// Reentry Streams
+//
+// Where the game reads its commands from and writes its output to. The
+// game loop works with any InputSource and OutputSink, so the engine can
+// be driven from the terminal, from files, or from memory.
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, IsTerminal, Write};
+use std::path::Path;
+
+pub trait InputSource {
+    // Returns None once there is no more input
+    fn read_line(&mut self) -> Option<String>;
+
+    // Interactive input is echoed by the terminal, anything else is not
+    fn is_interactive(&self) -> bool {
+        false
+    }
+
+    // Inputs that show their own prompt, such as a line editor
+    fn shows_prompt(&self) -> bool {
+        false
+    }
+
+    // The names the player could use right now, for inputs that complete them
+    fn set_nouns(&mut self, _nouns: Vec<String>) {}
+}
+
+impl<T: InputSource + ?Sized> InputSource for Box<T> {
+    fn read_line(&mut self) -> Option<String> {
+        (**self).read_line()
+    }
+
+    fn is_interactive(&self) -> bool {
+        (**self).is_interactive()
+    }
+
+    fn shows_prompt(&self) -> bool {
+        (**self).shows_prompt()
+    }
+
+    fn set_nouns(&mut self, nouns: Vec<String>) {
+        (**self).set_nouns(nouns)
+    }
+}
+
+pub trait OutputSink {
+    fn write(&mut self, text: &str) -> io::Result<()>;
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn read_from(reader: &mut impl BufRead) -> Option<String> {
+    let mut input_str = String::new();
+    match reader.read_line(&mut input_str) {
+        Ok(0) | Err(_) => None,
+        Ok(_) => Some(input_str.trim_end_matches(['\n', '\r']).to_string()),
+    }
+}
+
+#[derive(Default)]
+pub struct StdinInput;
+
+impl InputSource for StdinInput {
+    fn read_line(&mut self) -> Option<String> {
+        read_from(&mut io::stdin().lock())
+    }
+
+    fn is_interactive(&self) -> bool {
+        io::stdin().is_terminal()
+    }
+}
+
+#[derive(Default)]
+pub struct StdoutOutput;
+
+impl OutputSink for StdoutOutput {
+    fn write(&mut self, text: &str) -> io::Result<()> {
+        io::stdout().write_all(text.as_bytes())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        io::stdout().flush()
+    }
+}
+
+#[derive(Default)]
+pub struct MemoryInput {
+    pub lines: VecDeque<String>,
+}
+
+impl MemoryInput {
+    pub fn new(text: &str) -> MemoryInput {
+        MemoryInput {
+            lines: text.lines().map(String::from).collect(),
+        }
+    }
+}
+
+impl InputSource for MemoryInput {
+    fn read_line(&mut self) -> Option<String> {
+        self.lines.pop_front()
+    }
+}
+
+#[derive(Default)]
+pub struct MemoryOutput {
+    pub text: String,
+}
+
+impl OutputSink for MemoryOutput {
+    fn write(&mut self, text: &str) -> io::Result<()> {
+        self.text += text;
+        Ok(())
+    }
+}
+
+pub struct FileInput {
+    reader: BufReader<File>,
+}
+
+impl FileInput {
+    pub fn open(path: impl AsRef<Path>) -> io::Result<FileInput> {
+        Ok(FileInput {
+            reader: BufReader::new(File::open(path)?),
+        })
+    }
+}
+
+impl InputSource for FileInput {
+    fn read_line(&mut self) -> Option<String> {
+        read_from(&mut self.reader)
+    }
+}
+
+pub struct FileOutput {
+    file: File,
+}
+
+impl FileOutput {
+    pub fn create(path: impl AsRef<Path>) -> io::Result<FileOutput> {
+        Ok(FileOutput {
+            file: File::create(path)?,
+        })
+    }
+}
+
+impl OutputSink for FileOutput {
+    fn write(&mut self, text: &str) -> io::Result<()> {
+        self.file.write_all(text.as_bytes())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+// Reads everything from the first source, then carries on with the second
+pub struct ChainInput<A: InputSource, B: InputSource> {
+    first: Option<A>,
+    second: B,
+}
+
+impl<A: InputSource, B: InputSource> ChainInput<A, B> {
+    pub fn new(first: A, second: B) -> ChainInput<A, B> {
+        ChainInput {
+            first: Some(first),
+            second,
+        }
+    }
+}
+
+impl<A: InputSource, B: InputSource> InputSource for ChainInput<A, B> {
+    fn read_line(&mut self) -> Option<String> {
+        if let Some(first) = &mut self.first {
+            match first.read_line() {
+                Some(input_str) => return Some(input_str),
+                None => self.first = None,
+            }
+        }
+        self.second.read_line()
+    }
+
+    fn is_interactive(&self) -> bool {
+        match &self.first {
+            Some(first) => first.is_interactive(),
+            None => self.second.is_interactive(),
+        }
+    }
+
+    fn shows_prompt(&self) -> bool {
+        match &self.first {
+            Some(first) => first.shows_prompt(),
+            None => self.second.shows_prompt(),
+        }
+    }
+
+    fn set_nouns(&mut self, nouns: Vec<String>) {
+        if let Some(first) = &mut self.first {
+            first.set_nouns(nouns.clone());
+        }
+        self.second.set_nouns(nouns);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Stands in for the terminal
+    struct Interactive(MemoryInput);
+
+    impl InputSource for Interactive {
+        fn read_line(&mut self) -> Option<String> {
+            self.0.read_line()
+        }
+
+        fn is_interactive(&self) -> bool {
+            true
+        }
+    }
+
+    #[test]
+    fn chain_reads_the_first_source_then_the_second() {
+        let mut input = ChainInput::new(
+            MemoryInput::new("get photo\nlook"),
+            MemoryInput::new("go aft"),
+        );
+        assert_eq!(input.read_line().as_deref(), Some("get photo"));
+        assert_eq!(input.read_line().as_deref(), Some("look"));
+        assert_eq!(input.read_line().as_deref(), Some("go aft"));
+        assert_eq!(input.read_line(), None);
+        assert_eq!(input.read_line(), None);
+    }
+
+    #[test]
+    fn chain_is_interactive_once_it_reaches_the_terminal() {
+        let mut input = ChainInput::new(
+            MemoryInput::new("look"),
+            Interactive(MemoryInput::new("quit")),
+        );
+        assert!(!input.is_interactive());
+        assert_eq!(input.read_line().as_deref(), Some("look"));
+        assert_eq!(input.read_line().as_deref(), Some("quit"));
+        assert!(input.is_interactive());
+    }
+
+    #[test]
+    fn chain_skips_an_empty_first_source() {
+        let mut input = ChainInput::new(MemoryInput::default(), MemoryInput::new("look"));
+        assert_eq!(input.read_line().as_deref(), Some("look"));
+    }
+}
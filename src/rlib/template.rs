@@ -0,0 +1,390 @@
+//
+// Reentry Templates
+//
+// Game text can include placeholders that are filled in from the World
+// each time the text is shown:
+//
+//      {turn}              the number of turns taken so far
+//      {OBJECT.FIELD}      name, description, location, weight, capacity,
+//                          health or hidden of an object, or else one of its
+//                          properties
+//      {if CONDITION}...{else}...{end}
+//                          text shown only when CONDITION holds. The {else}
+//                          part is optional and ifs may be nested.
+//
+// OBJECT is the id of an object, 'player', or 'here' for the player's
+// location. A CONDITION is a value, which holds unless it is empty,
+// "false" or "0", or a comparison 'VALUE = TEXT', and either may start with
+// 'not'. A location shows as a name but compares with an id, 'player' or
+// 'here' as well. Write {{ for a literal '{'.
+use super::{ObjectId, World};
+
+#[derive(Debug)]
+enum Value {
+    Turn,
+    Field { object: String, field: String },
+}
+
+#[derive(Debug)]
+struct Condition {
+    negated: bool,
+    value: Value,
+    equals: Option<String>,
+}
+
+#[derive(Debug)]
+enum Token {
+    Text(String),
+    Value(Value),
+    If(Condition),
+    Else,
+    End,
+}
+
+fn parse_value(text: &str) -> Result<Value, String> {
+    match text.split_once('.') {
+        _ if text == "turn" => Ok(Value::Turn),
+        Some((object, field)) if !object.trim().is_empty() && !field.trim().is_empty() => {
+            Ok(Value::Field {
+                object: object.trim().to_string(),
+                field: field.trim().to_lowercase(),
+            })
+        }
+        _ => Err(format!("'{}' is not 'turn' or OBJECT.FIELD", text)),
+    }
+}
+
+fn parse_condition(text: &str) -> Result<Condition, String> {
+    let (negated, text) = match text.strip_prefix("not ") {
+        Some(rest) => (true, rest.trim()),
+        None => (false, text),
+    };
+
+    match text.split_once('=') {
+        Some((value, equals)) => Ok(Condition {
+            negated,
+            value: parse_value(value.trim())?,
+            equals: Some(equals.trim().to_string()),
+        }),
+        None => Ok(Condition {
+            negated,
+            value: parse_value(text)?,
+            equals: None,
+        }),
+    }
+}
+
+fn parse(text: &str) -> Result<Vec<Token>, String> {
+    let mut tokens: Vec<Token> = Vec::new();
+    // Whether each open {if} has had its {else} yet
+    let mut open_ifs: Vec<bool> = Vec::new();
+    let mut rest = text;
+
+    while let Some(open) = rest.find('{') {
+        tokens.push(Token::Text(rest[..open].to_string()));
+        rest = &rest[open..];
+
+        if let Some(after) = rest.strip_prefix("{{") {
+            tokens.push(Token::Text("{".into()));
+            rest = after;
+            continue;
+        }
+
+        let Some(close) = rest.find('}') else {
+            return Err("'{' is never closed".into());
+        };
+        let inner = rest[1..close].trim();
+
+        match inner {
+            "else" => match open_ifs.last_mut() {
+                Some(has_else) if !*has_else => {
+                    *has_else = true;
+                    tokens.push(Token::Else);
+                }
+                Some(_) => return Err("{if} has more than one {else}".into()),
+                None => return Err("{else} without {if}".into()),
+            },
+            "end" => {
+                if open_ifs.pop().is_none() {
+                    return Err("{end} without {if}".into());
+                }
+                tokens.push(Token::End);
+            }
+            _ => match inner.strip_prefix("if ") {
+                Some(condition) => {
+                    open_ifs.push(false);
+                    tokens.push(Token::If(parse_condition(condition.trim())?));
+                }
+                None => tokens.push(Token::Value(parse_value(inner)?)),
+            },
+        }
+        rest = &rest[close + 1..];
+    }
+
+    if !open_ifs.is_empty() {
+        return Err("{if} without {end}".into());
+    }
+    tokens.push(Token::Text(rest.to_string()));
+    Ok(tokens)
+}
+
+// 'player' is whoever is playing and 'here' is where they are, whatever
+// the game file calls them
+pub fn is_special_name(name: &str) -> bool {
+    name == "player" || name == "here"
+}
+
+fn find_object(world: &World, name: &str) -> Option<ObjectId> {
+    match name {
+        "player" => Some(world.player),
+        "here" => world.player_location(),
+        _ => world.find_id(name),
+    }
+}
+
+// None when the object does not exist. A missing property is just empty.
+fn evaluate(world: &World, value: &Value) -> Option<String> {
+    let (object, field) = match value {
+        Value::Turn => return Some(world.turn.to_string()),
+        Value::Field { object, field } => (object, field),
+    };
+    let object = &world[find_object(world, object)?];
+
+    Some(match field.as_str() {
+        "name" => object.labels[0].clone(),
+        "description" => object.description.clone(),
+        "location" => object
+            .location
+            .map(|location| world[location].labels[0].clone())
+            .unwrap_or_default(),
+        "weight" => object.weight.to_string(),
+        "capacity" => object.capacity.to_string(),
+        "health" => object.health.to_string(),
+        "hidden" => object.hidden.to_string(),
+        property => object.properties.get(property).cloned().unwrap_or_default(),
+    })
+}
+
+// A location is compared as an object, so it can be given by id, as
+// 'player' or 'here', or by name
+fn location_is(world: &World, object: &str, equals: &str) -> Option<bool> {
+    let target = find_object(world, equals)?;
+    Some(world[find_object(world, object)?].location == Some(target))
+}
+
+fn holds(world: &World, condition: &Condition) -> bool {
+    let value = evaluate(world, &condition.value).unwrap_or_default();
+    let result = match (&condition.value, &condition.equals) {
+        (Value::Field { object, field }, Some(equals)) if field == "location" => {
+            location_is(world, object, equals).unwrap_or_else(|| value.eq_ignore_ascii_case(equals))
+        }
+        (_, Some(equals)) => value.eq_ignore_ascii_case(equals),
+        (_, None) => !matches!(value.as_str(), "" | "false" | "0"),
+    };
+    result != condition.negated
+}
+
+// Conditions on their own, as dialogue uses them. An empty condition
+// always holds and one that cannot be read never does.
+pub fn condition_holds(text: &str, world: &World) -> bool {
+    if text.trim().is_empty() {
+        return true;
+    }
+    match parse_condition(text.trim()) {
+        Ok(condition) => holds(world, &condition),
+        Err(_) => false,
+    }
+}
+
+// Text that is not a valid template is shown as it is, and values of
+// objects that do not exist as {?}
+pub fn render(text: &str, world: &World) -> String {
+    if !text.contains('{') {
+        return text.to_string();
+    }
+    let Ok(tokens) = parse(text) else {
+        return text.to_string();
+    };
+
+    let mut output = String::new();
+    let mut shown: Vec<bool> = Vec::new();
+
+    for token in &tokens {
+        let visible = shown.iter().all(|&branch| branch);
+        match token {
+            Token::Text(text) if visible => output.push_str(text),
+            Token::Value(value) if visible => match evaluate(world, value) {
+                Some(value) => output.push_str(&value),
+                None => output.push_str("{?}"),
+            },
+            Token::If(condition) => shown.push(holds(world, condition)),
+            Token::Else => {
+                if let Some(branch) = shown.last_mut() {
+                    *branch = !*branch;
+                }
+            }
+            Token::End => {
+                shown.pop();
+            }
+            _ => (),
+        }
+    }
+
+    output
+}
+
+fn add_name(names: &mut Vec<String>, value: Value) {
+    if let Value::Field { object, .. } = value {
+        if !is_special_name(&object) && !names.contains(&object) {
+            names.push(object);
+        }
+    }
+}
+
+// The objects a template refers to by name, or why it is not a template
+pub fn object_names(text: &str) -> Result<Vec<String>, String> {
+    let mut names: Vec<String> = Vec::new();
+    for token in parse(text)? {
+        match token {
+            Token::Value(value) => add_name(&mut names, value),
+            Token::If(condition) => add_name(&mut names, condition.value),
+            _ => (),
+        }
+    }
+    Ok(names)
+}
+
+// The objects a condition refers to by name, or why it is not a condition
+pub fn condition_names(text: &str) -> Result<Vec<String>, String> {
+    let mut names: Vec<String> = Vec::new();
+    if !text.trim().is_empty() {
+        add_name(&mut names, parse_condition(text.trim())?.value);
+    }
+    Ok(names)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rlib::SavedWorld;
+
+    fn world() -> World {
+        let saved: SavedWorld = ron::from_str(
+            r#"(objects: [
+                (id: "player", kind: Actor, labels: ["Yourself"], description: "you",
+                 location: "bridge"),
+                (id: "bridge", kind: Location, labels: ["Bridge"], description: "the bridge"),
+                (id: "locker", kind: Item, labels: ["Locker"], description: "a locker",
+                 location: "bridge", properties: {"open": "true", "code": "0"}),
+                (id: "pen", kind: Item, labels: ["Pen"], description: "a pen", location: "player"),
+            ])"#,
+        )
+        .unwrap();
+        saved.try_into().unwrap()
+    }
+
+    #[test]
+    fn values_are_filled_in() {
+        let world = world();
+        assert_eq!(render("turn {turn}", &world), "turn 0");
+        assert_eq!(
+            render("{pen.name} in {pen.location}", &world),
+            "Pen in Yourself"
+        );
+        assert_eq!(render("{here.description}", &world), "the bridge");
+        assert_eq!(
+            render("{player.weight}", &world),
+            world[world.player].weight.to_string()
+        );
+        assert_eq!(render("[{locker.missing}]", &world), "[]");
+        assert_eq!(render("{nothing.name}", &world), "{?}");
+    }
+
+    #[test]
+    fn conditions_choose_text() {
+        let world = world();
+        assert_eq!(
+            render("{if locker.open}open{else}shut{end}", &world),
+            "open"
+        );
+        assert_eq!(
+            render("{if locker.code}set{else}unset{end}", &world),
+            "unset"
+        );
+        assert_eq!(
+            render("{if not locker.missing}no value{end}", &world),
+            "no value"
+        );
+        assert_eq!(
+            render("{if here.name = BRIDGE}bridge{end}", &world),
+            "bridge"
+        );
+        assert_eq!(
+            render(
+                "{if locker.open}a{if locker.code}b{else}c{end}d{end}",
+                &world
+            ),
+            "acd"
+        );
+    }
+
+    #[test]
+    fn locations_compare_by_id() {
+        let world = world();
+        assert_eq!(
+            render("{if pen.location = player}held{end}", &world),
+            "held"
+        );
+        assert_eq!(
+            render("{if locker.location = here}here{end}", &world),
+            "here"
+        );
+        assert_eq!(
+            render("{if locker.location = bridge}by id{end}", &world),
+            "by id"
+        );
+        assert_eq!(
+            render("{if pen.location = Yourself}by name{end}", &world),
+            "by name"
+        );
+        assert_eq!(render("{if pen.location = bridge}wrong{end}", &world), "");
+    }
+
+    #[test]
+    fn bad_templates_are_shown_as_they_are() {
+        let world = world();
+        for text in [
+            "{if locker.open}never closed",
+            "{end}",
+            "{pen}",
+            "{if a.b}{else}{else}{end}",
+        ] {
+            assert_eq!(render(text, &world), text);
+            assert!(object_names(text).is_err(), "{}", text);
+        }
+        assert_eq!(render("a {{literal}", &world), "a {literal}");
+    }
+
+    #[test]
+    fn names_are_found_without_special_ones() {
+        assert_eq!(
+            object_names("{pen.name} {if locker.open}{here.name}{player.name}{end} {pen.weight}"),
+            Ok(vec!["pen".to_string(), "locker".to_string()])
+        );
+        assert_eq!(
+            condition_names("not pen.location = player"),
+            Ok(vec!["pen".to_string()])
+        );
+        assert_eq!(condition_names(""), Ok(Vec::new()));
+        assert!(condition_names("turn.").is_err());
+    }
+
+    #[test]
+    fn conditions_on_their_own() {
+        let world = world();
+        assert!(condition_holds("", &world));
+        assert!(condition_holds("locker.open", &world));
+        assert!(!condition_holds("not locker.open", &world));
+        assert!(!condition_holds("nonsense", &world));
+    }
+}
@@ -0,0 +1,418 @@
+//
+// Reentry Transcripts
+//
+// Plays a transcript of commands against a World and checks that the game
+// still responds the way the transcript expects. Transcripts look like this:
+//
+//      # Lines starting with '#' are comments. Blank lines are ignored.
+//      > get photo
+//      You pick up Glossy Photo.
+//      > look
+//      Bridge
+//      You are in *.
+//      ...
+//      ~ an? (passage|bulkhead) .*
+//      @ Glossy Photo in Yourself
+//      @ Pen not in Yourself
+//
+// Each '>' line is a command and the lines after it are the expected
+// output. In expected output '*' matches any text within a line, '...' on
+// its own matches any number of lines, and lines starting with '~ ' are
+// regular expressions. A command with no expected output may print
+// anything. '@' lines check where an object is, naming objects by their id
+// or, failing that, their first label.
+use super::session::Session;
+use super::{ObjectId, Settings, World};
+use regex::Regex;
+use std::fmt;
+
+enum Expect {
+    Line(Regex),
+    AnyLines,
+}
+
+enum Step {
+    Command {
+        line_no: usize,
+        input: String,
+        expected: Vec<Expect>,
+        expected_text: Vec<String>,
+    },
+    Check {
+        line_no: usize,
+        object: String,
+        location: String,
+        negate: bool,
+    },
+}
+
+pub struct Transcript {
+    steps: Vec<Step>,
+}
+
+#[derive(Debug)]
+pub struct TranscriptError {
+    pub line_no: usize,
+    pub message: String,
+}
+
+impl fmt::Display for TranscriptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line_no, self.message)
+    }
+}
+
+#[derive(Debug)]
+pub struct Failure {
+    pub line_no: usize,
+    pub step: String,
+    pub expected: Vec<String>,
+    pub actual: Vec<String>,
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "line {}: {}", self.line_no, self.step)?;
+        for line in &self.expected {
+            writeln!(f, "    - {}", line)?;
+        }
+        for line in &self.actual {
+            writeln!(f, "    + {}", line)?;
+        }
+        Ok(())
+    }
+}
+
+fn glob_to_regex(pattern: &str) -> String {
+    let parts: Vec<String> = pattern.split('*').map(regex::escape).collect();
+    format!("^{}$", parts.join(".*"))
+}
+
+fn output_lines(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .map(|line| line.trim_end().to_string())
+        .filter(|line| !line.is_empty())
+        .collect()
+}
+
+fn lines_match(expected: &[Expect], actual: &[String]) -> bool {
+    match expected.first() {
+        None => actual.is_empty(),
+        Some(Expect::AnyLines) => {
+            (0..=actual.len()).any(|skip| lines_match(&expected[1..], &actual[skip..]))
+        }
+        Some(Expect::Line(pattern)) => {
+            !actual.is_empty()
+                && pattern.is_match(&actual[0])
+                && lines_match(&expected[1..], &actual[1..])
+        }
+    }
+}
+
+fn find_object(world: &World, name: &str) -> Option<ObjectId> {
+    world.find_id(name).or_else(|| {
+        world
+            .iter()
+            .find(|(_, object)| {
+                object
+                    .labels
+                    .first()
+                    .is_some_and(|label| label.eq_ignore_ascii_case(name))
+            })
+            .map(|(id, _)| id)
+    })
+}
+
+impl Transcript {
+    pub fn parse(text: &str) -> Result<Transcript, TranscriptError> {
+        let mut steps: Vec<Step> = Vec::new();
+
+        for (index, raw_line) in text.lines().enumerate() {
+            let line_no = index + 1;
+            let line = raw_line.trim_end();
+
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if let Some(input) = line.strip_prefix('>') {
+                steps.push(Step::Command {
+                    line_no,
+                    input: input.trim().to_string(),
+                    expected: Vec::new(),
+                    expected_text: Vec::new(),
+                });
+            } else if let Some(check) = line.strip_prefix('@') {
+                let (object, location, negate) = match check.split_once(" not in ") {
+                    Some((object, location)) => (object, location, true),
+                    None => match check.split_once(" in ") {
+                        Some((object, location)) => (object, location, false),
+                        None => {
+                            return Err(TranscriptError {
+                                line_no,
+                                message: "checks must read '@ OBJECT in LOCATION'".into(),
+                            })
+                        }
+                    },
+                };
+                steps.push(Step::Check {
+                    line_no,
+                    object: object.trim().to_string(),
+                    location: location.trim().to_string(),
+                    negate,
+                });
+            } else {
+                let Some(Step::Command {
+                    expected,
+                    expected_text,
+                    ..
+                }) = steps.last_mut()
+                else {
+                    return Err(TranscriptError {
+                        line_no,
+                        message: "expected output must follow a '>' command".into(),
+                    });
+                };
+
+                let pattern = if line == "..." {
+                    Expect::AnyLines
+                } else if let Some(regex_str) = line.strip_prefix("~ ") {
+                    let regex_res = Regex::new(&format!("^(?:{})$", regex_str));
+                    match regex_res {
+                        Ok(regex) => Expect::Line(regex),
+                        Err(regex_err) => {
+                            return Err(TranscriptError {
+                                line_no,
+                                message: regex_err.to_string(),
+                            })
+                        }
+                    }
+                } else {
+                    // Escaped patterns are always valid regular expressions
+                    Expect::Line(Regex::new(&glob_to_regex(line)).unwrap())
+                };
+                expected.push(pattern);
+                expected_text.push(line.to_string());
+            }
+        }
+
+        Ok(Transcript { steps })
+    }
+
+    pub fn len(&self) -> usize {
+        self.steps.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    // The commands in the transcript, in order, without what they expect
+    pub fn inputs(&self) -> Vec<String> {
+        self.steps
+            .iter()
+            .filter_map(|step| match step {
+                Step::Command { input, .. } => Some(input.clone()),
+                Step::Check { .. } => None,
+            })
+            .collect()
+    }
+
+    // Plays the transcript through a Session, as a player would
+    pub fn run(&self, world: World) -> Vec<Failure> {
+        // Output is compared line by line as plain text, so it is never
+        // wrapped or coloured
+        let settings = Settings {
+            width: Some(0),
+            color: false,
+            ..Settings::default()
+        };
+        let mut session = Session::new(world, settings);
+        let mut failures: Vec<Failure> = Vec::new();
+
+        for step in &self.steps {
+            match step {
+                Step::Command {
+                    line_no,
+                    input,
+                    expected,
+                    expected_text,
+                } => {
+                    let output = session.step(input).output;
+                    let actual = output_lines(&output);
+
+                    if !expected.is_empty() && !lines_match(expected, &actual) {
+                        failures.push(Failure {
+                            line_no: *line_no,
+                            step: format!("> {}", input),
+                            expected: expected_text.clone(),
+                            actual,
+                        });
+                    }
+                }
+                Step::Check {
+                    line_no,
+                    object,
+                    location,
+                    negate,
+                } => {
+                    let world = &session.world;
+                    let step = format!(
+                        "@ {} {} {}",
+                        object,
+                        if *negate { "not in" } else { "in" },
+                        location
+                    );
+                    let (object_opt, location_opt) =
+                        (find_object(world, object), find_object(world, location));
+
+                    let actual = match (object_opt, location_opt) {
+                        (None, _) => vec![format!("there is no object '{}'", object)],
+                        (_, None) => vec![format!("there is no object '{}'", location)],
+                        (Some(object_idx), Some(location_idx)) => {
+                            let held = world.is_holding(Some(location_idx), Some(object_idx));
+                            if held != *negate {
+                                continue;
+                            }
+                            let actual_loc = world[object_idx]
+                                .location
+                                .map(|loc| world[loc].labels[0].to_string())
+                                .unwrap_or("nowhere".into());
+                            vec![format!("{} is in {}", object, actual_loc)]
+                        }
+                    };
+                    failures.push(Failure {
+                        line_no: *line_no,
+                        step,
+                        expected: Vec::new(),
+                        actual,
+                    });
+                }
+            }
+        }
+
+        failures
+    }
+}
+
+// Writes one command and the game's response in transcript form
+pub fn record(input: &str, output: &str) -> String {
+    let mut text = format!("> {}\n", input.trim());
+    for line in output_lines(output) {
+        // Keep recorded text from being read back as anything but text
+        if line.contains('*')
+            || line == "..."
+            || line.starts_with("~ ")
+            || line.starts_with(['>', '@', '#'])
+        {
+            text += &format!("~ {}\n", regex::escape(&line));
+        } else {
+            text += &format!("{}\n", line);
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // What a command expecting these lines expects
+    fn expected(lines: &[&str]) -> Vec<Expect> {
+        let text = format!("> look\n{}\n", lines.join("\n"));
+        let mut transcript = Transcript::parse(&text).unwrap();
+        match transcript.steps.remove(0) {
+            Step::Command { expected, .. } => expected,
+            Step::Check { .. } => unreachable!(),
+        }
+    }
+
+    fn matches(expected_lines: &[&str], actual: &[&str]) -> bool {
+        let actual: Vec<String> = actual.iter().map(|line| line.to_string()).collect();
+        lines_match(&expected(expected_lines), &actual)
+    }
+
+    #[test]
+    fn lines_match_exactly() {
+        assert!(matches(
+            &["Bridge", "You are in the bridge."],
+            &["Bridge", "You are in the bridge."]
+        ));
+        assert!(!matches(&["Bridge"], &["Galley"]));
+        assert!(!matches(&["Bridge"], &["Bridge", "You are in the bridge."]));
+        assert!(!matches(&["Bridge", "You are in the bridge."], &["Bridge"]));
+    }
+
+    #[test]
+    fn stars_match_within_a_line() {
+        assert!(matches(&["You are in *."], &["You are in the galley."]));
+        assert!(matches(&["*"], &[""]));
+        assert!(!matches(&["You are in *."], &["You are in the galley"]));
+    }
+
+    #[test]
+    fn other_characters_are_not_special() {
+        assert!(matches(&["Is it (really) $5?"], &["Is it (really) $5?"]));
+        assert!(!matches(&["a.c"], &["abc"]));
+        assert_eq!(glob_to_regex("a.*b"), "^a\\..*b$");
+    }
+
+    #[test]
+    fn dots_match_any_number_of_lines() {
+        assert!(matches(&["Bridge", "..."], &["Bridge"]));
+        assert!(matches(&["Bridge", "..."], &["Bridge", "one", "two"]));
+        assert!(matches(&["...", "two"], &["one", "two"]));
+        assert!(matches(&["one", "...", "three"], &["one", "two", "three"]));
+        assert!(!matches(&["...", "four"], &["one", "two", "three"]));
+    }
+
+    #[test]
+    fn regular_expressions_match_whole_lines() {
+        assert!(matches(
+            &["~ an? (glossy|silver) .*"],
+            &["a silver cryosuit"]
+        ));
+        assert!(!matches(
+            &["~ an? (glossy|silver) .*"],
+            &["the glossy photo"]
+        ));
+        assert!(!matches(&["~ glossy"], &["a glossy photo"]));
+    }
+
+    #[test]
+    fn bad_transcripts_are_reported_by_line() {
+        let err = Transcript::parse("# comment\nstray output\n")
+            .err()
+            .unwrap();
+        assert_eq!(err.line_no, 2);
+
+        let err = Transcript::parse("> look\n~ (unclosed\n").err().unwrap();
+        assert_eq!(err.line_no, 2);
+
+        let err = Transcript::parse("> look\n@ photo on table\n")
+            .err()
+            .unwrap();
+        assert_eq!(err.line_no, 2);
+    }
+
+    #[test]
+    fn checks_and_comments_are_read() {
+        let transcript =
+            Transcript::parse("# a comment\n\n> get pen\n@ pen in player\n@ pen not in copilot\n")
+                .unwrap();
+        assert_eq!(transcript.len(), 3);
+    }
+
+    #[test]
+    fn recorded_output_reads_back_as_itself() {
+        let output = "Bridge\n*beep*\n...\n> not a command\n~ not a regex\n";
+        let recording = record("look", output);
+        let mut transcript = Transcript::parse(&recording).unwrap();
+        let Step::Command { expected, .. } = transcript.steps.remove(0) else {
+            unreachable!();
+        };
+        assert!(lines_match(&expected, &output_lines(output)));
+        assert!(!lines_match(&expected, &output_lines("Bridge\nbeep\n")));
+    }
+}
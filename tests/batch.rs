@@ -0,0 +1,78 @@
+//
+// Reentry Batch
+//
+// Runs the reentry binary in batch mode against the shipped game file and
+// checks how each kind of run ends
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+fn batch(commands: &str, final_state: bool) -> Output {
+    let game_file = concat!(env!("CARGO_MANIFEST_DIR"), "/src/game_file.ron");
+    let mut args = vec!["--game-file", game_file, "--batch", "-"];
+    if final_state {
+        args.push("--final-state");
+    }
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_reentry"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(commands.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn running_out_of_commands_succeeds() {
+    let output = batch("# a comment\n\nlook\nget photo\n", false);
+    assert_eq!(output.status.code(), Some(0));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.starts_with("> look\n"));
+    assert!(stdout.contains("> get photo\n"));
+}
+
+#[test]
+fn a_failed_command_exits_with_2() {
+    let output = batch("look\nget unicorn\nlook\n", false);
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("command 2 'get unicorn'"), "{}", stderr);
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout)
+            .matches("> look")
+            .count(),
+        1
+    );
+}
+
+#[test]
+fn quitting_exits_with_3() {
+    let output = batch("look\nquit\nlook\n", false);
+    assert_eq!(output.status.code(), Some(3));
+    assert!(output.stderr.is_empty());
+}
+
+#[test]
+fn winning_exits_with_3() {
+    let output = batch("get photo\ngo aft\ngo aft\nget cryosuit\nlook\n", false);
+    assert_eq!(output.status.code(), Some(3));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("You have done everything you set out to do."));
+    assert!(!stdout.contains("> look"));
+}
+
+#[test]
+fn final_state_is_a_game_file() {
+    let output = batch("get photo\n", true);
+    assert_eq!(output.status.code(), Some(0));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.trim_start().starts_with('('), "{}", stdout);
+    assert!(!stdout.contains("> get photo"));
+}
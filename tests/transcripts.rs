@@ -0,0 +1,34 @@
+//
+// Reentry Transcripts
+//
+// Plays every transcript in transcripts/ against the shipped game file,
+// as 'reentry test' does
+use reentry::rlib::transcript::Transcript;
+use reentry::rlib::World;
+use std::fs::{read_dir, read_to_string};
+use std::path::Path;
+
+#[test]
+fn transcripts_pass() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let game_file = root.join("src/game_file.ron");
+    let world = World::read_from_file(&game_file.to_string_lossy()).unwrap();
+
+    let mut paths: Vec<_> = read_dir(root.join("transcripts"))
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "txt"))
+        .collect();
+    paths.sort();
+    assert!(!paths.is_empty(), "no transcripts to play");
+
+    let mut report = String::new();
+    for path in paths {
+        let transcript = Transcript::parse(&read_to_string(&path).unwrap())
+            .unwrap_or_else(|transcript_err| panic!("{}: {}", path.display(), transcript_err));
+        for failure in transcript.run(world.clone()) {
+            report += &format!("{}: {}", path.display(), failure);
+        }
+    }
+    assert!(report.is_empty(), "\n{}", report);
+}